@@ -0,0 +1,162 @@
+use console::Style;
+use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+
+use super::args::{Args, JobsArgs, ServeArgs, SubmitArgs};
+use yt_grab::core::daemon::{Daemon, Job, JobItemState, JobRequest};
+use yt_grab::core::grabber::Grabber;
+use yt_grab::core::http;
+use yt_grab::error::{AppError, Result, EXIT_SUCCESS};
+use yt_grab::model::format::MediaFormat;
+
+/// The `serve` command: run jobs submitted over HTTP until Ctrl-C.
+pub async fn run_serve(args: &Args, serve: &ServeArgs) -> Result<i32> {
+    let format = MediaFormat::from_extension(&serve.format)
+        .ok_or_else(|| AppError::UnknownFormat(serve.format.clone()))?;
+    let template = Grabber::builder()
+        .format(format)
+        .output_dir(serve.output.clone())
+        .concurrency(serve.concurrency)
+        .sleep_requests(args.sleep_requests()?)
+        .network(args.network()?)
+        .filter(args.entry_filter()?)
+        .write_info_json(args.write_info_json)
+        .tabs(args.tab.clone())
+        .build();
+
+    std::fs::create_dir_all(&serve.output)?;
+    let history = serve
+        .history
+        .clone()
+        .unwrap_or_else(|| serve.output.join(".yt-grab-history.json"));
+    let daemon = Daemon::new(template, Some(history))?;
+    let listener = TcpListener::bind(&serve.listen).await?;
+    println!(
+        "\n  {} Listening on http://{}, downloading {format} into {}\n",
+        Style::new().bold().cyan().apply_to("yt-grab"),
+        listener.local_addr()?,
+        serve.output.display()
+    );
+
+    let shutdown = CancellationToken::new();
+    let stop = shutdown.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            eprintln!(
+                "\n  {} Shutting down, cancelling running jobs...",
+                Style::new().bold().yellow().apply_to("!!")
+            );
+            stop.cancel();
+        }
+    });
+    daemon.serve(listener, serve.parallel_jobs, shutdown).await?;
+    Ok(EXIT_SUCCESS)
+}
+
+/// Send a request to the daemon, returning the body of a successful response.
+async fn call(addr: &str, method: &str, path: &str, body: Option<String>) -> Result<String> {
+    let failed = |reason: String| AppError::Daemon {
+        addr: addr.to_string(),
+        reason,
+    };
+    let (status, body) = http::request(addr, method, path, body.as_deref())
+        .await
+        .map_err(|e| failed(e.to_string()))?;
+    if (200..300).contains(&status) {
+        return Ok(body);
+    }
+    let message = serde_json::from_str::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|v| v["error"].as_str().map(String::from))
+        .unwrap_or_else(|| format!("HTTP {status}"));
+    Err(failed(message))
+}
+
+/// The `submit` command.
+pub async fn run_submit(args: &Args, submit: &SubmitArgs) -> Result<i32> {
+    let request = JobRequest {
+        url: submit.url.clone(),
+        items: args.items.clone(),
+        format: submit.format.clone(),
+        sections: submit.sections.clone(),
+        output: submit.output.clone(),
+    };
+    let body = serde_json::to_string(&request)?;
+    let job: Job = serde_json::from_str(&call(&submit.server, "POST", "/jobs", Some(body)).await?)?;
+    println!(
+        "\n  {} Job {} queued: {}\n",
+        Style::new().bold().cyan().apply_to(">>"),
+        job.id,
+        job.request.url
+    );
+    Ok(EXIT_SUCCESS)
+}
+
+fn label<T: std::fmt::Debug>(state: T) -> String {
+    format!("{state:?}").to_lowercase()
+}
+
+fn print_job_line(job: &Job) {
+    let title = job.title.as_deref().unwrap_or(&job.request.url);
+    let progress = if job.items.is_empty() {
+        String::new()
+    } else {
+        format!("{}/{}", job.succeeded(), job.items.len())
+    };
+    println!(
+        "  {} {:<12} {:>7}  {title}",
+        Style::new().yellow().apply_to(format!("#{:<4}", job.id)),
+        label(job.state),
+        progress
+    );
+}
+
+fn print_job(job: &Job) {
+    let dim = Style::new().dim();
+    println!();
+    print_job_line(job);
+    for item in &job.items {
+        let state = match item.state {
+            JobItemState::Downloading | JobItemState::Processing => {
+                format!("{} {:.0}%", label(item.state), item.percent)
+            }
+            state => label(state),
+        };
+        println!("        {:<16} {}", dim.apply_to(state), item.title);
+        if let Some(error) = &item.error {
+            println!("        {}", Style::new().red().apply_to(error));
+        }
+    }
+    if let Some(error) = &job.error {
+        println!("  {} {error}", Style::new().bold().red().apply_to("!!"));
+    }
+    println!();
+}
+
+/// The `jobs` command: list jobs, show one, or cancel one.
+pub async fn run_jobs(jobs: &JobsArgs) -> Result<i32> {
+    let server = &jobs.server;
+    if let Some(id) = jobs.cancel {
+        call(server, "DELETE", &format!("/jobs/{id}"), None).await?;
+        println!("\n  {} Job {id} cancelled\n", Style::new().bold().cyan().apply_to(">>"));
+        return Ok(EXIT_SUCCESS);
+    }
+    if let Some(id) = jobs.id {
+        let job: Job = serde_json::from_str(&call(server, "GET", &format!("/jobs/{id}"), None).await?)?;
+        print_job(&job);
+        return Ok(EXIT_SUCCESS);
+    }
+
+    let path = if jobs.history { "/history" } else { "/jobs" };
+    let list: Vec<Job> = serde_json::from_str(&call(server, "GET", path, None).await?)?;
+    println!();
+    if list.is_empty() {
+        let what = if jobs.history { "finished" } else { "queued or running" };
+        println!("  No {what} jobs");
+    }
+    for job in &list {
+        print_job_line(job);
+    }
+    println!();
+    Ok(EXIT_SUCCESS)
+}
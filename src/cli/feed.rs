@@ -0,0 +1,50 @@
+use console::Style;
+use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+
+use super::args::FeedArgs;
+use yt_grab::core::feed::{Feed, DEFAULT_ADDR as FEED_ADDR, FEED_FILE};
+use yt_grab::error::{Result, EXIT_SUCCESS};
+
+/// The `feed` command: write the feed, then serve it if asked to.
+pub async fn run_feed(feed_args: &FeedArgs) -> Result<i32> {
+    // clap requires --base-url without --serve
+    let base_url = match &feed_args.base_url {
+        Some(url) => url.clone(),
+        None => format!("http://{}", feed_args.serve.as_deref().unwrap_or(FEED_ADDR)),
+    };
+    let feed = Feed::new(&feed_args.dir, &base_url, feed_args.title.clone())?;
+    let output = feed_args
+        .output
+        .clone()
+        .unwrap_or_else(|| feed_args.dir.join(FEED_FILE));
+    let episodes = feed.write(&output)?;
+
+    let header = Style::new().bold().cyan();
+    println!(
+        "\n  {} Wrote {} with {episodes} episode(s)",
+        header.apply_to("yt-grab"),
+        output.display()
+    );
+    let Some(addr) = &feed_args.serve else {
+        println!();
+        return Ok(EXIT_SUCCESS);
+    };
+
+    let listener = TcpListener::bind(addr).await?;
+    println!(
+        "  {} Serving {} at {}/{FEED_FILE}\n",
+        header.apply_to(">>"),
+        feed.dir.display(),
+        feed.base_url
+    );
+    let shutdown = CancellationToken::new();
+    let stop = shutdown.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            stop.cancel();
+        }
+    });
+    feed.serve(listener, shutdown).await?;
+    Ok(EXIT_SUCCESS)
+}
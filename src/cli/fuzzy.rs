@@ -0,0 +1,284 @@
+use std::io::IsTerminal;
+
+use console::{truncate_str, Key, Style, Term};
+
+use yt_grab::core::range_parser::parse_ranges;
+use yt_grab::error::{AppError, Result};
+
+/// Number of matching items shown at once.
+const PAGE_SIZE: usize = 10;
+
+/// Whether the terminal supports the fuzzy prompt (both ends must be a TTY).
+pub fn is_available() -> bool {
+    std::io::stdin().is_terminal() && Term::stdout().is_term()
+}
+
+/// Score how well `query` matches `text` as a case-insensitive subsequence.
+/// Consecutive matches and matches at word starts score higher; `None` means
+/// no match.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let query: Vec<char> = query.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let Some(&first) = query.first() else {
+        return Some(0);
+    };
+
+    // Greedy matching from every possible start, keeping the best run
+    let best = (0..text.len())
+        .filter(|&start| text[start] == first)
+        .filter_map(|start| score_from(&query, &text, start))
+        .max()?;
+
+    // Prefer shorter texts on ties
+    Some(best * 100 - text.len() as i64)
+}
+
+fn score_from(query: &[char], text: &[char], start: usize) -> Option<i64> {
+    let mut score = 0;
+    let mut pos = start;
+    let mut last_match: Option<usize> = None;
+
+    for &qc in query {
+        let found = text[pos..].iter().position(|&c| c == qc)? + pos;
+        score += 1;
+        if last_match.is_some_and(|last| last + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        last_match = Some(found);
+        pos = found + 1;
+    }
+    Some(score)
+}
+
+/// Does the query start with one of the range keywords, as a whole word?
+fn starts_with_keyword(query: &str, keywords: &[&str]) -> bool {
+    let q = query.trim_start().to_lowercase();
+    keywords.iter().any(|kw| {
+        q.strip_prefix(kw)
+            .is_some_and(|rest| !rest.starts_with(|c: char| c.is_alphanumeric()))
+    })
+}
+
+/// Does the query begin the way a range expression would?
+fn starts_like_range(query: &str) -> bool {
+    query
+        .trim_start()
+        .starts_with(|c: char| c.is_ascii_digit() || matches!(c, '!' | '^' | '-'))
+        || starts_with_keyword(query, &["all", "last", "random"])
+}
+
+/// Is the query a valid range expression over `total` items? Anything else,
+/// including titles that merely start with a digit, is a title search.
+fn is_range(query: &str, total: usize) -> bool {
+    let query = query.trim();
+    !query.is_empty() && parse_ranges(query, total).is_ok()
+}
+
+/// Type-to-filter multi-select: typing narrows the list, space ticks the
+/// highlighted item, Enter confirms. A query that parses as a range
+/// (e.g. `1,3-5`) is accepted as-is when nothing is ticked.
+pub struct FuzzyMultiSelect<'a> {
+    pub prompt: &'a str,
+    pub items: &'a [String],
+    /// Confirming with nothing ticked and an empty query returns `None`
+    pub allow_empty: bool,
+}
+
+struct State {
+    query: String,
+    checked: Vec<bool>,
+    cursor: usize,
+    message: Option<String>,
+}
+
+impl FuzzyMultiSelect<'_> {
+    fn matches(&self, query: &str) -> Vec<usize> {
+        // Range expressions select by index, so keep the whole list visible
+        if is_range(query, self.items.len()) {
+            return (0..self.items.len()).collect();
+        }
+
+        let mut scored: Vec<(usize, i64)> = self
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| fuzzy_score(query, item).map(|s| (i, s)))
+            .collect();
+        if !query.is_empty() {
+            scored.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        }
+        scored.into_iter().map(|(i, _)| i).collect()
+    }
+
+    fn render(&self, term: &Term, state: &State, matches: &[usize]) -> std::io::Result<usize> {
+        let prompt_style = Style::new().bold().green();
+        let dim = Style::new().dim();
+        let width = term.size().1 as usize;
+        let mut lines = 0;
+
+        term.write_line(&format!(
+            "  {} {} {}",
+            prompt_style.apply_to(">>"),
+            self.prompt,
+            state.query
+        ))?;
+        lines += 1;
+
+        let start = state.cursor.saturating_sub(PAGE_SIZE - 1);
+        for (row, &i) in matches.iter().enumerate().skip(start).take(PAGE_SIZE) {
+            let pointer = if row == state.cursor { ">" } else { " " };
+            let check = if state.checked[i] { "[x]" } else { "[ ]" };
+            // Wrapped lines would break clearing on redraw
+            let line = format!(
+                "  {pointer} {check} {}{:>3}{} {}",
+                Style::new().yellow().apply_to("["),
+                Style::new().yellow().bold().apply_to(i + 1),
+                Style::new().yellow().apply_to("]"),
+                self.items[i]
+            );
+            term.write_line(&truncate_str(&line, width.saturating_sub(1), "…"))?;
+            lines += 1;
+        }
+
+        let ticked = state.checked.iter().filter(|c| **c).count();
+        let footer = match &state.message {
+            Some(msg) => Style::new().red().apply_to(msg.clone()).to_string(),
+            None => dim
+                .apply_to(format!(
+                    "{} match(es), {ticked} ticked · space tick · enter confirm · ranges like 1,3-5 work too",
+                    matches.len()
+                ))
+                .to_string(),
+        };
+        term.write_line(&truncate_str(
+            &format!("    {footer}"),
+            width.saturating_sub(1),
+            "…",
+        ))?;
+        Ok(lines + 1)
+    }
+
+    /// Returns 1-based indices, or `None` for an empty confirmation.
+    pub fn interact(&self) -> Result<Option<Vec<usize>>> {
+        let term = Term::stdout();
+        let mut state = State {
+            query: String::new(),
+            checked: vec![false; self.items.len()],
+            cursor: 0,
+            message: None,
+        };
+
+        term.hide_cursor()?;
+        let result = self.run(&term, &mut state);
+        term.show_cursor()?;
+        result
+    }
+
+    fn run(&self, term: &Term, state: &mut State) -> Result<Option<Vec<usize>>> {
+        loop {
+            let matches = self.matches(&state.query);
+            state.cursor = state.cursor.min(matches.len().saturating_sub(1));
+            let drawn = self.render(term, state, &matches)?;
+
+            let key = term.read_key().map_err(|_| AppError::Cancelled)?;
+            term.clear_last_lines(drawn)?;
+            state.message = None;
+
+            match key {
+                Key::Escape | Key::CtrlC => return Err(AppError::Cancelled),
+                Key::ArrowUp => state.cursor = state.cursor.saturating_sub(1),
+                Key::ArrowDown => state.cursor += 1,
+                Key::PageUp => state.cursor = state.cursor.saturating_sub(PAGE_SIZE),
+                Key::PageDown => state.cursor += PAGE_SIZE,
+                Key::Backspace => {
+                    state.query.pop();
+                    state.cursor = 0;
+                }
+                // Spaces are part of the text after range keywords ("last 5")
+                Key::Char(' ') if !starts_with_keyword(&state.query, &["last", "random"]) => {
+                    if let Some(&i) = matches.get(state.cursor) {
+                        state.checked[i] = !state.checked[i];
+                    }
+                }
+                Key::Char(c) if !c.is_control() => {
+                    state.query.push(c);
+                    state.cursor = 0;
+                }
+                Key::Enter => match self.confirm(state, &matches) {
+                    Ok(selection) => return Ok(selection),
+                    Err(e) => state.message = Some(e.to_string()),
+                },
+                _ => {}
+            }
+        }
+    }
+
+    fn confirm(&self, state: &State, matches: &[usize]) -> Result<Option<Vec<usize>>> {
+        let ticked: Vec<usize> = (0..self.items.len())
+            .filter(|&i| state.checked[i])
+            .map(|i| i + 1)
+            .collect();
+        if !ticked.is_empty() {
+            return Ok(Some(ticked));
+        }
+
+        let query = state.query.trim();
+        if query.is_empty() {
+            return if self.allow_empty {
+                Ok(None)
+            } else {
+                Err(AppError::InvalidRange {
+                    input: String::new(),
+                    position: 1,
+                    reason: "nothing selected".into(),
+                })
+            };
+        }
+
+        let range = parse_ranges(query, self.items.len());
+        if let Ok(selection) = range {
+            return Ok(Some(selection));
+        }
+
+        // A plain title search confirms the highlighted match; when nothing
+        // matches, a query that looked like a range gets the range error
+        match matches.get(state.cursor) {
+            Some(&i) => Ok(Some(vec![i + 1])),
+            None if starts_like_range(query) => range.map(Some),
+            None => Err(AppError::InvalidRange {
+                input: query.to_string(),
+                position: 1,
+                reason: "no matching items".into(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert!(fuzzy_score("rst", "Rust tutorial").is_some());
+        assert!(fuzzy_score("xyz", "Rust tutorial").is_none());
+        // Consecutive, word-start matches rank higher
+        assert!(fuzzy_score("tut", "Rust tutorial") > fuzzy_score("tut", "The utter truth"));
+    }
+
+    #[test]
+    fn test_is_range() {
+        assert!(is_range("1,3-5", 10));
+        assert!(is_range("^1", 10));
+        assert!(is_range("last 3", 10));
+        assert!(!is_range("rust", 10));
+        assert!(!is_range("alligators", 10));
+        // Titles that start like a range are searched for
+        assert!(!is_range("2024 recap", 10));
+        assert!(!is_range("1984", 10));
+        assert!(!is_range("", 10));
+    }
+}
@@ -1,27 +1,42 @@
 use console::Style;
 use dialoguer::{Input, Select};
 
-use crate::core::range_parser::parse_ranges;
-use crate::error::{AppError, Result};
-use crate::model::format::MediaFormat;
-use crate::model::video::VideoInfo;
-
-pub fn display_playlist(title: &Option<String>, videos: &[VideoInfo]) {
+use super::fuzzy::{self, FuzzyMultiSelect};
+use yt_grab::core::playlist::Listing;
+use yt_grab::core::range_parser::parse_ranges;
+use yt_grab::error::{AppError, Result};
+use yt_grab::model::format::MediaFormat;
+use yt_grab::model::video::VideoInfo;
+
+pub fn display_playlist(title: &Option<String>, videos: &[VideoInfo], filtered_out: usize) {
     let header = Style::new().bold().cyan();
     let dim = Style::new().dim();
 
     if let Some(t) = title {
         println!("\n  {} {}", header.apply_to("Playlist:"), t);
     }
-    println!(
-        "  {} {} items\n",
-        header.apply_to("Found:"),
-        videos.len()
-    );
+    if filtered_out > 0 {
+        println!(
+            "  {} {} items {}\n",
+            header.apply_to("Found:"),
+            videos.len(),
+            dim.apply_to(format!("({filtered_out} filtered out)"))
+        );
+    } else {
+        println!(
+            "  {} {} items\n",
+            header.apply_to("Found:"),
+            videos.len()
+        );
+    }
 
     for v in videos {
+        let position = v
+            .position_display()
+            .map(|p| format!("{} ", dim.apply_to(p)))
+            .unwrap_or_default();
         println!(
-            "  {}{:>3}{} {} {}{}{}",
+            "  {}{:>3}{} {position}{} {}{}{}",
             Style::new().yellow().apply_to("["),
             Style::new().yellow().bold().apply_to(v.index),
             Style::new().yellow().apply_to("]"),
@@ -34,10 +49,71 @@ pub fn display_playlist(title: &Option<String>, videos: &[VideoInfo]) {
     println!();
 }
 
-pub fn prompt_selection(total: usize) -> Result<Vec<usize>> {
+/// List the tabs or playlists of a channel and ask which to list. Returns
+/// their entry numbers.
+pub fn prompt_tabs(listing: &Listing, fuzzy: bool) -> Result<Vec<usize>> {
+    let header = Style::new().bold().cyan();
+    let playlists: Vec<_> = listing.playlists().collect();
+
+    if let Some(t) = &listing.title {
+        println!("\n  {} {}", header.apply_to("Channel:"), t);
+    }
+    if fuzzy && fuzzy::is_available() {
+        let titles: Vec<String> = playlists.iter().map(|(_, sub)| sub.title.clone()).collect();
+        let picked = FuzzyMultiSelect {
+            prompt: "Select tabs or playlists to list (type to filter):",
+            items: &titles,
+            allow_empty: false,
+        }
+        .interact()?;
+        return Ok(picked
+            .unwrap_or_default()
+            .into_iter()
+            .map(|i| playlists[i - 1].0)
+            .collect());
+    }
+
+    println!(
+        "  {} {} tabs or playlists\n",
+        header.apply_to("Found:"),
+        playlists.len()
+    );
+    for (number, sub) in &playlists {
+        println!(
+            "  {}{:>3}{} {}",
+            Style::new().yellow().apply_to("["),
+            Style::new().yellow().bold().apply_to(number),
+            Style::new().yellow().apply_to("]"),
+            sub.title
+        );
+    }
+    println!(
+        "\n  {} Choose what to list (e.g. videos, shorts, 1,3 or 'all'):",
+        Style::new().bold().green().apply_to(">>")
+    );
+
+    let input: String = Input::new()
+        .with_prompt("  ")
+        .interact_text()
+        .map_err(|_| AppError::Cancelled)?;
+    listing.choose(&input)
+}
+
+pub fn prompt_selection(videos: &[VideoInfo], fuzzy: bool) -> Result<Vec<usize>> {
+    if fuzzy && fuzzy::is_available() {
+        let titles: Vec<String> = videos.iter().map(|v| v.title.clone()).collect();
+        let picked = FuzzyMultiSelect {
+            prompt: "Select items (type to filter):",
+            items: &titles,
+            allow_empty: false,
+        }
+        .interact()?;
+        return Ok(picked.unwrap_or_default());
+    }
+
     let prompt_style = Style::new().bold().green();
     println!(
-        "  {} Enter selection (e.g. 1,3-5 or 'all'):",
+        "  {} Enter selection (e.g. 1,3-5, 10-, ^1, all,!4, last 5 or 'all'):",
         prompt_style.apply_to(">>")
     );
 
@@ -46,12 +122,12 @@ pub fn prompt_selection(total: usize) -> Result<Vec<usize>> {
         .interact_text()
         .map_err(|_| AppError::Cancelled)?;
 
-    parse_ranges(&input, total)
+    parse_ranges(&input, videos.len())
 }
 
 /// Display chapters for a video and prompt the user to select which ones to download.
 /// Returns the selected (start_time, end_time) pairs, or empty vec for "full video".
-pub fn prompt_chapter_selection(video: &mut VideoInfo) -> Result<()> {
+pub fn prompt_chapter_selection(video: &mut VideoInfo, fuzzy: bool) -> Result<()> {
     let header = Style::new().bold().cyan();
     let dim = Style::new().dim();
     let prompt_style = Style::new().bold().green();
@@ -63,6 +139,24 @@ pub fn prompt_chapter_selection(video: &mut VideoInfo) -> Result<()> {
         video.chapters.len()
     );
 
+    if fuzzy && fuzzy::is_available() {
+        let titles: Vec<String> = video
+            .chapters
+            .iter()
+            .map(|ch| format!("{} ({})", ch.title, ch.time_display()))
+            .collect();
+        let picked = FuzzyMultiSelect {
+            prompt: "Select chapters (Enter with nothing ticked for full video):",
+            items: &titles,
+            allow_empty: true,
+        }
+        .interact()?;
+        if let Some(indices) = picked {
+            select_chapters(video, &indices);
+        }
+        return Ok(());
+    }
+
     for (i, ch) in video.chapters.iter().enumerate() {
         println!(
             "  {}{:>3}{} {} {}{}{}",
@@ -95,7 +189,13 @@ pub fn prompt_chapter_selection(video: &mut VideoInfo) -> Result<()> {
     }
 
     let indices = parse_ranges(input, video.chapters.len())?;
+    select_chapters(video, &indices);
 
+    Ok(())
+}
+
+/// Turn 1-based chapter indices into download sections.
+fn select_chapters(video: &mut VideoInfo, indices: &[usize]) {
     video.selected_sections = indices
         .iter()
         .map(|&i| {
@@ -103,8 +203,6 @@ pub fn prompt_chapter_selection(video: &mut VideoInfo) -> Result<()> {
             (ch.start_time, ch.end_time)
         })
         .collect();
-
-    Ok(())
 }
 
 pub fn prompt_format() -> Result<MediaFormat> {
@@ -0,0 +1,105 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use console::Style;
+use indicatif::MultiProgress;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use yt_grab::error::Result;
+
+/// Progress bars currently on screen; terminal messages are printed around
+/// them so the bars aren't torn.
+static PROGRESS: Mutex<Option<MultiProgress>> = Mutex::new(None);
+
+/// Set while the full-screen TUI owns the terminal.
+static TERMINAL_PAUSED: AtomicBool = AtomicBool::new(false);
+
+/// `log` backend: messages up to `terminal` go to stderr, and everything up
+/// to debug level (or `terminal`, if higher) to the log file.
+struct Logger {
+    terminal: LevelFilter,
+    file: Option<Mutex<File>>,
+    file_level: LevelFilter,
+    start: Instant,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.terminal
+            || (self.file.is_some() && metadata.level() <= self.file_level)
+    }
+
+    fn log(&self, record: &Record) {
+        // Only our own messages; dependencies are not interesting here
+        if !record.target().starts_with("yt_grab") {
+            return;
+        }
+        let level = record.level();
+
+        if let Some(file) = &self.file {
+            if level <= self.file_level {
+                let secs = self.start.elapsed().as_secs_f64();
+                let mut file = file.lock().unwrap();
+                let _ = writeln!(file, "[{secs:9.3}s] {level:<5} {}", record.args());
+            }
+        }
+
+        if level <= self.terminal && !TERMINAL_PAUSED.load(Ordering::Relaxed) {
+            let style = match level {
+                Level::Error => Style::new().bold().red(),
+                Level::Warn => Style::new().bold().yellow(),
+                Level::Info => Style::new().cyan(),
+                Level::Debug | Level::Trace => Style::new().dim(),
+            };
+            let line = format!(
+                "  {} {}",
+                style.apply_to(level.as_str().to_lowercase()),
+                record.args()
+            );
+            match PROGRESS.lock().unwrap().as_ref() {
+                Some(multi) => multi.suspend(|| eprintln!("{line}")),
+                None => eprintln!("{line}"),
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &self.file {
+            let _ = file.lock().unwrap().flush();
+        }
+    }
+}
+
+/// Install the logger. `log_file` is truncated.
+pub fn init(terminal: LevelFilter, log_file: Option<&Path>) -> Result<()> {
+    let file = log_file.map(File::create).transpose()?;
+    let file_level = terminal.max(LevelFilter::Debug);
+    let max = if file.is_some() { file_level } else { terminal };
+
+    let logger = Logger {
+        terminal,
+        file: file.map(Mutex::new),
+        file_level,
+        start: Instant::now(),
+    };
+    // Only fails if called twice
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        log::set_max_level(max);
+    }
+    Ok(())
+}
+
+/// Route terminal messages around `multi` while it is drawn.
+pub fn set_progress(multi: Option<MultiProgress>) {
+    *PROGRESS.lock().unwrap() = multi;
+}
+
+/// Stop (or resume) writing to the terminal, e.g. while the TUI is shown.
+/// The log file is unaffected.
+pub fn pause_terminal(paused: bool) {
+    TERMINAL_PAUSED.store(paused, Ordering::Relaxed);
+}
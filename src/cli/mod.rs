@@ -1,2 +1,7 @@
 pub mod args;
+pub mod daemon;
+pub mod feed;
+pub mod fuzzy;
 pub mod interactive;
+pub mod logging;
+pub mod watch;
@@ -0,0 +1,209 @@
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use console::Style;
+use tokio_util::sync::CancellationToken;
+
+use super::args::{Args, WatchArgs};
+use yt_grab::core::grabber::{Grabber, Playlist};
+use yt_grab::core::progress::LogReporter;
+use yt_grab::core::watch::{load_sources, parse_interval, Archive, WatchSource};
+use yt_grab::error::{AppError, Result, EXIT_ALL_FAILED, EXIT_PARTIAL, EXIT_SUCCESS};
+use yt_grab::model::format::MediaFormat;
+use yt_grab::model::video::date_from_timestamp;
+
+/// What one check of every source found.
+#[derive(Default)]
+struct Cycle {
+    new: usize,
+    downloaded: usize,
+    /// New items recorded without downloading (`--skip-existing`)
+    skipped: usize,
+    failures: Vec<String>,
+}
+
+impl Cycle {
+    fn exit_code(&self) -> i32 {
+        if self.failures.is_empty() {
+            EXIT_SUCCESS
+        } else if self.downloaded == 0 {
+            EXIT_ALL_FAILED
+        } else {
+            EXIT_PARTIAL
+        }
+    }
+}
+
+/// `YYYY-MM-DD HH:MM:SS UTC`
+fn now_utc() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64);
+    let date = date_from_timestamp(secs);
+    let time = secs.rem_euclid(86_400);
+    format!(
+        "{}-{}-{} {:02}:{:02}:{:02} UTC",
+        &date[..4],
+        &date[4..6],
+        &date[6..],
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+/// The `watch` command: check the sources every interval (or once) and
+/// download what the archive doesn't list yet.
+pub async fn run_watch(args: &Args, watch: &WatchArgs) -> Result<i32> {
+    let mut sources: Vec<WatchSource> = watch.urls.iter().map(|u| WatchSource::new(u)).collect();
+    if let Some(path) = &watch.sources {
+        sources.extend(load_sources(path)?);
+    }
+    if sources.is_empty() {
+        return Err(AppError::InvalidSources("no sources to watch".to_string()));
+    }
+    let interval = parse_interval(&watch.interval)?;
+    let format = MediaFormat::from_extension(&watch.format)
+        .ok_or_else(|| AppError::UnknownFormat(watch.format.clone()))?;
+    let template = Grabber::builder()
+        .format(format)
+        .output_dir(watch.output.clone())
+        .concurrency(watch.concurrency)
+        .sleep_requests(args.sleep_requests()?)
+        .network(args.network()?)
+        .filter(args.entry_filter()?)
+        .write_info_json(args.write_info_json)
+        .tabs(args.tab.clone())
+        .build();
+
+    std::fs::create_dir_all(&watch.output)?;
+    let archive_path = watch
+        .archive
+        .clone()
+        .unwrap_or_else(|| watch.output.join(".yt-grab-archive"));
+    let mut archive = Archive::open(archive_path)?;
+
+    let header = Style::new().bold().cyan();
+    println!(
+        "\n  {} Watching {} source(s), {} id(s) archived\n",
+        header.apply_to("yt-grab"),
+        sources.len(),
+        archive.len()
+    );
+
+    let cancel = CancellationToken::new();
+    crate::spawn_interrupt_handler(cancel.clone());
+    let mut first = true;
+    loop {
+        let skip = first && watch.skip_existing;
+        first = false;
+        let cycle = check_sources(args, &template, &sources, &mut archive, skip, &cancel).await?;
+        print_cycle(&cycle, sources.len());
+
+        if cancel.is_cancelled() {
+            return Err(AppError::Cancelled);
+        }
+        if watch.once {
+            return Ok(cycle.exit_code());
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = cancel.cancelled() => return Err(AppError::Cancelled),
+        }
+    }
+}
+
+/// Check every source once, downloading its new items and archiving the
+/// ones that succeeded.
+async fn check_sources(
+    args: &Args,
+    template: &Grabber,
+    sources: &[WatchSource],
+    archive: &mut Archive,
+    skip: bool,
+    cancel: &CancellationToken,
+) -> Result<Cycle> {
+    let mut cycle = Cycle::default();
+    for source in sources {
+        if cancel.is_cancelled() {
+            break;
+        }
+        let mut builder = template.to_builder();
+        if let Some(format) = source.format {
+            builder = builder.format(format);
+        }
+        if let Some(dir) = &source.output {
+            builder = builder.output_dir(template.config().output_dir.join(dir));
+        }
+        if !args.quiet {
+            builder = builder.reporter(Arc::new(LogReporter::default()));
+        }
+        let grabber = builder.build();
+
+        let fetcher = grabber.clone();
+        let url = source.url.clone();
+        let fetched = tokio::task::spawn_blocking(move || fetcher.fetch(&url))
+            .await
+            .unwrap_or_else(|e| Err(std::io::Error::other(e).into()));
+        let playlist = match fetched {
+            Ok(playlist) => playlist,
+            Err(AppError::AllFiltered(_)) => continue,
+            Err(e) => {
+                log::warn!("{}: {e}", source.url);
+                cycle.failures.push(format!("{}: {e}", source.url));
+                continue;
+            }
+        };
+
+        let listed = playlist.videos.len();
+        let videos = archive.new_entries(playlist.videos);
+        log::info!("{}: {} new of {listed}", source.url, videos.len());
+        if videos.is_empty() {
+            continue;
+        }
+        cycle.new += videos.len();
+        std::fs::create_dir_all(&grabber.config().output_dir)?;
+
+        if skip {
+            for video in &videos {
+                archive.add(&video.id)?;
+            }
+            cycle.skipped += videos.len();
+            continue;
+        }
+
+        let playlist = Playlist { videos, ..playlist };
+        let results = grabber.download(&playlist, cancel.clone()).await;
+        for (video, result) in playlist.videos.iter().zip(&results) {
+            if result.success {
+                archive.add(&video.id)?;
+                cycle.downloaded += 1;
+            } else if !result.cancelled {
+                let error = result.error.as_deref().unwrap_or("unknown error");
+                cycle.failures.push(format!("\"{}\": {error}", video.title));
+            }
+        }
+    }
+    Ok(cycle)
+}
+
+fn print_cycle(cycle: &Cycle, sources: usize) {
+    let mut line = format!("Checked {sources} source(s): {} new", cycle.new);
+    if cycle.skipped > 0 {
+        line.push_str(&format!(", {} recorded as already seen", cycle.skipped));
+    } else {
+        line.push_str(&format!(", {} downloaded", cycle.downloaded));
+    }
+    if !cycle.failures.is_empty() {
+        line.push_str(&format!(", {} failed", cycle.failures.len()));
+    }
+    println!(
+        "\n  {} {line}",
+        Style::new().dim().apply_to(format!("[{}]", now_utc()))
+    );
+    log::info!("{line}");
+    for failure in &cycle.failures {
+        println!("    {} {failure}", Style::new().red().apply_to("✗"));
+    }
+    println!();
+}
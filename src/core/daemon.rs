@@ -0,0 +1,592 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpListener;
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+
+use super::filter::parse_duration;
+use super::grabber::Grabber;
+use super::http::{self, Request, Response};
+use super::progress::{Phase, ProgressEvent};
+
+use crate::error::{AppError, Result};
+use crate::model::format::MediaFormat;
+
+/// Where `serve` listens, and clients connect, by default.
+pub const DEFAULT_ADDR: &str = "127.0.0.1:8765";
+
+/// A download job, as sent to `POST /jobs`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JobRequest {
+    pub url: String,
+    /// Items to download, like `"1,3-5"`; all if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub items: Option<String>,
+    /// `mp3`, `opus`, `m4a`, `mp4` or `mkv`; the daemon's format if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// Time ranges like `"1:30-4:00"` to download from each item instead of
+    /// the whole of it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sections: Vec<String>,
+    /// Subdirectory of the daemon's output directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Fetching,
+    Downloading,
+    /// Finished; individual items may still have failed
+    Done,
+    /// Nothing could be downloaded
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    pub fn is_finished(self) -> bool {
+        matches!(self, Self::Done | Self::Failed | Self::Cancelled)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobItemState {
+    Waiting,
+    Paused,
+    Downloading,
+    Converting,
+    Processing,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobItem {
+    pub id: String,
+    pub title: String,
+    pub state: JobItemState,
+    /// Of the current phase (download or processing)
+    pub percent: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: u64,
+    pub request: JobRequest,
+    pub state: JobState,
+    /// Unix time
+    pub submitted: u64,
+    #[serde(default)]
+    pub finished: Option<u64>,
+    /// Playlist title, once fetched
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub items: Vec<JobItem>,
+    /// Why the job failed or stopped before downloading
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl Job {
+    pub fn succeeded(&self) -> usize {
+        self.items
+            .iter()
+            .filter(|it| it.state == JobItemState::Done)
+            .count()
+    }
+
+    fn apply(&mut self, event: ProgressEvent) {
+        if let ProgressEvent::Queued { item, id, title } = event {
+            if self.items.len() <= item {
+                self.items.resize_with(item + 1, || JobItem {
+                    id: String::new(),
+                    title: String::new(),
+                    state: JobItemState::Waiting,
+                    percent: 0.0,
+                    error: None,
+                });
+            }
+            self.items[item].id = id;
+            self.items[item].title = title;
+            return;
+        }
+
+        let (item, state, percent, error) = match event {
+            ProgressEvent::Queued { .. } => return,
+            ProgressEvent::Paused { item, .. } => (item, JobItemState::Paused, None, None),
+            ProgressEvent::Resumed { item } => (item, JobItemState::Waiting, None, None),
+            ProgressEvent::Started { item } => (item, JobItemState::Downloading, Some(0.0), None),
+            ProgressEvent::Phase { item, phase } => match phase {
+                Phase::Converting => (item, JobItemState::Converting, None, None),
+                Phase::Processing => (item, JobItemState::Processing, Some(0.0), None),
+            },
+            ProgressEvent::Progress { item, percent } => match self.items.get(item) {
+                Some(it) => (item, it.state, Some(percent), None),
+                None => return,
+            },
+            ProgressEvent::Retry { .. } => return,
+            ProgressEvent::Finished { item } => (item, JobItemState::Done, Some(100.0), None),
+            ProgressEvent::Failed { item, error } => {
+                (item, JobItemState::Failed, None, Some(error))
+            }
+            ProgressEvent::Cancelled { item } => (item, JobItemState::Cancelled, None, None),
+        };
+        if let Some(it) = self.items.get_mut(item) {
+            it.state = state;
+            if let Some(percent) = percent {
+                it.percent = percent;
+            }
+            it.error = error;
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Parse a section like `"1:30-4:00"` (seconds or `[H:]MM:SS` on both sides).
+pub fn parse_section(input: &str) -> Result<(f64, f64)> {
+    let invalid = || {
+        AppError::InvalidJob(format!(
+            "invalid section \"{input}\" (expected e.g. 1:30-4:00)"
+        ))
+    };
+    let (start, end) = input.split_once('-').ok_or_else(invalid)?;
+    let (start, end) = (
+        parse_duration(start).map_err(|_| invalid())?,
+        parse_duration(end).map_err(|_| invalid())?,
+    );
+    if end <= start {
+        return Err(invalid());
+    }
+    Ok((start, end))
+}
+
+/// A relative path below the output directory, without `..`.
+fn output_subdir(input: &str) -> Result<PathBuf> {
+    let path = Path::new(input);
+    if path.components().all(|c| matches!(c, Component::Normal(_))) {
+        Ok(path.to_path_buf())
+    } else {
+        Err(AppError::InvalidJob(format!(
+            "output \"{input}\" must be a subdirectory of the daemon's output directory"
+        )))
+    }
+}
+
+struct Jobs {
+    /// Oldest first; finished jobs loaded from the history file included
+    list: Vec<Job>,
+    next_id: u64,
+    /// Of jobs that are queued or running
+    cancels: HashMap<u64, CancellationToken>,
+}
+
+/// Accepts download jobs over a local HTTP/JSON API and runs them one after
+/// another (or a few at a time) with the settings of a template `Grabber`.
+///
+/// | Request | |
+/// |---|---|
+/// | `POST /jobs` | Submit a `JobRequest`; returns the `Job` |
+/// | `GET /jobs` | Queued and running jobs, with per-item progress |
+/// | `GET /jobs/{id}` | One job |
+/// | `DELETE /jobs/{id}` | Cancel a queued or running job |
+/// | `GET /history` | Finished jobs, newest first (`?limit=N`) |
+#[derive(Clone)]
+pub struct Daemon {
+    template: Grabber,
+    jobs: Arc<Mutex<Jobs>>,
+    queued: Arc<Notify>,
+    history: Option<PathBuf>,
+}
+
+impl Daemon {
+    /// Jobs download into `template`'s output directory, in its format unless
+    /// they ask for another. Finished jobs are kept in `history`, which is
+    /// read back here if it exists.
+    pub fn new(template: Grabber, history: Option<PathBuf>) -> Result<Self> {
+        let list: Vec<Job> = match &history {
+            Some(path) if path.exists() => {
+                serde_json::from_str(&std::fs::read_to_string(path)?)?
+            }
+            _ => Vec::new(),
+        };
+        let next_id = list.iter().map(|j| j.id).max().unwrap_or(0) + 1;
+        Ok(Self {
+            template,
+            jobs: Arc::new(Mutex::new(Jobs {
+                list,
+                next_id,
+                cancels: HashMap::new(),
+            })),
+            queued: Arc::new(Notify::new()),
+            history,
+        })
+    }
+
+    /// Validate and queue a job.
+    pub fn submit(&self, request: JobRequest) -> Result<Job> {
+        if request.url.trim().is_empty() {
+            return Err(AppError::InvalidJob("missing url".to_string()));
+        }
+        if let Some(format) = &request.format {
+            MediaFormat::from_extension(format)
+                .ok_or_else(|| AppError::InvalidJob(format!("unknown format \"{format}\"")))?;
+        }
+        for section in &request.sections {
+            parse_section(section)?;
+        }
+        if let Some(output) = &request.output {
+            output_subdir(output)?;
+        }
+
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = Job {
+            id: jobs.next_id,
+            request,
+            state: JobState::Queued,
+            submitted: now(),
+            finished: None,
+            title: None,
+            items: Vec::new(),
+            error: None,
+        };
+        jobs.next_id += 1;
+        jobs.list.push(job.clone());
+        jobs.cancels.insert(job.id, CancellationToken::new());
+        drop(jobs);
+
+        log::info!("Job {} queued: {}", job.id, job.request.url);
+        self.queued.notify_one();
+        Ok(job)
+    }
+
+    pub fn job(&self, id: u64) -> Option<Job> {
+        let jobs = self.jobs.lock().unwrap();
+        jobs.list.iter().find(|j| j.id == id).cloned()
+    }
+
+    /// Queued and running jobs, oldest first.
+    pub fn active(&self) -> Vec<Job> {
+        let jobs = self.jobs.lock().unwrap();
+        jobs.list
+            .iter()
+            .filter(|j| !j.state.is_finished())
+            .cloned()
+            .collect()
+    }
+
+    /// Finished jobs, newest first.
+    pub fn history(&self) -> Vec<Job> {
+        let jobs = self.jobs.lock().unwrap();
+        let mut finished: Vec<_> = jobs
+            .list
+            .iter()
+            .filter(|j| j.state.is_finished())
+            .cloned()
+            .collect();
+        finished.sort_by(|a, b| b.finished.cmp(&a.finished).then(b.id.cmp(&a.id)));
+        finished
+    }
+
+    /// Cancel a queued or running job. Returns false if it has finished or
+    /// doesn't exist.
+    pub fn cancel(&self, id: u64) -> bool {
+        let jobs = self.jobs.lock().unwrap();
+        match jobs.cancels.get(&id) {
+            Some(cancel) => {
+                cancel.cancel();
+                log::info!("Job {id} cancelled");
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn update(&self, id: u64, f: impl FnOnce(&mut Job)) {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(job) = jobs.list.iter_mut().find(|j| j.id == id) {
+            f(job);
+        }
+    }
+
+    fn save_history(&self) {
+        let Some(path) = &self.history else {
+            return;
+        };
+        let finished: Vec<Job> = {
+            let jobs = self.jobs.lock().unwrap();
+            jobs.list
+                .iter()
+                .filter(|j| j.state.is_finished())
+                .cloned()
+                .collect()
+        };
+        let written = serde_json::to_string_pretty(&finished)
+            .map_err(AppError::from)
+            .and_then(|json| Ok(std::fs::write(path, json)?));
+        if let Err(e) = written {
+            log::warn!("Could not save the job history to {}: {e}", path.display());
+        }
+    }
+
+    /// The oldest queued job, marked as fetching.
+    fn take_queued(&self) -> Option<(u64, JobRequest, CancellationToken)> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.list.iter_mut().find(|j| j.state == JobState::Queued)?;
+        job.state = JobState::Fetching;
+        let (id, request) = (job.id, job.request.clone());
+        let cancel = jobs.cancels.get(&id).cloned().unwrap_or_default();
+        Some((id, request, cancel))
+    }
+
+    /// Run queued jobs until `shutdown` fires.
+    async fn work(&self, shutdown: CancellationToken) {
+        loop {
+            let queued = self.queued.notified();
+            tokio::pin!(queued);
+            queued.as_mut().enable();
+
+            match self.take_queued() {
+                Some((id, request, cancel)) => {
+                    // Shutting down cancels the running job too
+                    let stop = shutdown.clone();
+                    let job_cancel = cancel.clone();
+                    let forward = tokio::spawn(async move {
+                        stop.cancelled().await;
+                        job_cancel.cancel();
+                    });
+                    self.run_job(id, request, cancel).await;
+                    forward.abort();
+                    self.jobs.lock().unwrap().cancels.remove(&id);
+                    self.save_history();
+                }
+                None => {
+                    tokio::select! {
+                        _ = &mut queued => {}
+                        _ = shutdown.cancelled() => return,
+                    }
+                }
+            }
+            if shutdown.is_cancelled() {
+                return;
+            }
+        }
+    }
+
+    async fn run_job(&self, id: u64, request: JobRequest, cancel: CancellationToken) {
+        let finish = |state: JobState, error: Option<String>| {
+            self.update(id, |job| {
+                job.state = state;
+                job.error = error;
+                job.finished = Some(now());
+            });
+        };
+        if cancel.is_cancelled() {
+            return finish(JobState::Cancelled, None);
+        }
+
+        let config = self.template.config();
+        let mut builder = self.template.to_builder();
+        if let Some(format) = request.format.as_deref().and_then(MediaFormat::from_extension) {
+            builder = builder.format(format);
+        }
+        if let Some(Ok(subdir)) = request.output.as_deref().map(output_subdir) {
+            builder = builder.output_dir(config.output_dir.join(subdir));
+        }
+        let daemon = self.clone();
+        let grabber = builder
+            .on_event(move |event| daemon.update(id, |job| job.apply(event)))
+            .build();
+        log::info!("Job {id} started: {}", request.url);
+
+        // Listing shells out to yt-dlp and blocks
+        let fetcher = grabber.clone();
+        let (url, items) = (request.url.clone(), request.items.clone());
+        let fetched = tokio::task::spawn_blocking(move || {
+            let mut playlist = fetcher.fetch(&url)?;
+            if let Some(items) = &items {
+                playlist.select(items)?;
+            }
+            Ok::<_, AppError>(playlist)
+        })
+        .await;
+        let mut playlist = match fetched {
+            Ok(Ok(playlist)) => playlist,
+            Ok(Err(e)) => {
+                log::warn!("Job {id} failed: {e}");
+                return finish(JobState::Failed, Some(e.to_string()));
+            }
+            Err(e) => return finish(JobState::Failed, Some(e.to_string())),
+        };
+        if cancel.is_cancelled() {
+            return finish(JobState::Cancelled, None);
+        }
+
+        let sections: Vec<_> = request
+            .sections
+            .iter()
+            .filter_map(|s| parse_section(s).ok())
+            .collect();
+        for video in &mut playlist.videos {
+            video.selected_sections = sections.clone();
+        }
+        self.update(id, |job| {
+            job.state = JobState::Downloading;
+            job.title = playlist.title.clone();
+        });
+
+        let results = grabber.download(&playlist, cancel.clone()).await;
+        let succeeded = results.iter().filter(|r| r.success).count();
+        log::info!(
+            "Job {id} finished: {succeeded} of {} item(s) downloaded",
+            results.len()
+        );
+        if cancel.is_cancelled() {
+            finish(JobState::Cancelled, None);
+        } else if succeeded == 0 {
+            finish(JobState::Failed, Some("every item failed".to_string()));
+        } else {
+            finish(JobState::Done, None);
+        }
+    }
+
+    /// Answer one API request made to the daemon listening on `local`.
+    fn handle(&self, request: &Request, local: SocketAddr) -> Response {
+        // Web pages can't reach the API: a DNS-rebound page sends its own
+        // host name, and a cross-site JSON post needs a CORS preflight,
+        // which is never answered
+        if !request.is_direct(local) {
+            return Response::error(403, "requests must address the daemon by IP or localhost");
+        }
+        let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+        match (request.method.as_str(), segments.as_slice()) {
+            ("POST", ["jobs"]) if !request.is_json() => {
+                Response::error(415, "jobs must be sent as application/json")
+            }
+            ("POST", ["jobs"]) => match serde_json::from_slice::<JobRequest>(&request.body) {
+                Ok(job) => match self.submit(job) {
+                    Ok(job) => Response::json(201, &job),
+                    Err(e) => Response::error(400, e.to_string()),
+                },
+                Err(e) => Response::error(400, format!("invalid job: {e}")),
+            },
+            ("GET", ["jobs"]) => Response::json(200, &self.active()),
+            ("GET", ["jobs", id]) => match id.parse().ok().and_then(|id| self.job(id)) {
+                Some(job) => Response::json(200, &job),
+                None => Response::error(404, format!("no job {id}")),
+            },
+            ("DELETE", ["jobs", id]) => match id.parse().ok() {
+                Some(id) if self.cancel(id) => Response::json(200, &self.job(id)),
+                Some(id) if self.job(id).is_some() => {
+                    Response::error(409, format!("job {id} has already finished"))
+                }
+                _ => Response::error(404, format!("no job {id}")),
+            },
+            ("GET", ["history"]) => {
+                let mut history = self.history();
+                if let Some(limit) = request.query_param("limit").and_then(|l| l.parse().ok()) {
+                    history.truncate(limit);
+                }
+                Response::json(200, &history)
+            }
+            (_, ["jobs"] | ["jobs", _] | ["history"]) => Response::error(405, "method not allowed"),
+            _ => Response::error(404, format!("no such endpoint: {}", request.path)),
+        }
+    }
+
+    /// Answer API requests on `listener` and run jobs, `parallel` at a time,
+    /// until `shutdown` fires. Running jobs are cancelled on shutdown.
+    pub async fn serve(
+        &self,
+        listener: TcpListener,
+        parallel: usize,
+        shutdown: CancellationToken,
+    ) -> Result<()> {
+        let workers: Vec<_> = (0..parallel.max(1))
+            .map(|_| {
+                let daemon = self.clone();
+                let shutdown = shutdown.clone();
+                tokio::spawn(async move { daemon.work(shutdown).await })
+            })
+            .collect();
+
+        let local = listener.local_addr()?;
+        loop {
+            let (mut stream, peer) = tokio::select! {
+                accepted = listener.accept() => accepted?,
+                _ = shutdown.cancelled() => break,
+            };
+            let daemon = self.clone();
+            tokio::spawn(async move {
+                let (reader, writer) = stream.split();
+                let response = match http::read_request(reader).await {
+                    Ok(Some(request)) => {
+                        log::debug!("{peer}: {} {}", request.method, request.path);
+                        daemon.handle(&request, local)
+                    }
+                    Ok(None) => return,
+                    Err(e) => Response::read_error(&e),
+                };
+                if let Err(e) = http::write_response(writer, &response, false).await {
+                    log::debug!("{peer}: {e}");
+                }
+            });
+        }
+
+        for worker in workers {
+            let _ = worker.await;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_section() {
+        assert_eq!(parse_section("1:30-4:00").unwrap(), (90.0, 240.0));
+        assert_eq!(parse_section("10-20").unwrap(), (10.0, 20.0));
+        assert!(parse_section("4:00-1:30").is_err());
+        assert!(parse_section("1:30").is_err());
+    }
+
+    #[test]
+    fn test_submit_validates() {
+        let daemon = Daemon::new(Grabber::builder().build(), None).unwrap();
+        let job = |f: fn(&mut JobRequest)| {
+            let mut request = JobRequest {
+                url: "https://youtu.be/x".to_string(),
+                ..JobRequest::default()
+            };
+            f(&mut request);
+            daemon.submit(request)
+        };
+        assert!(job(|r| r.format = Some("wav".into())).is_err());
+        assert!(job(|r| r.output = Some("../elsewhere".into())).is_err());
+        assert!(job(|r| r.output = Some("/tmp".into())).is_err());
+
+        let queued = job(|r| r.output = Some("talks/2024".into())).unwrap();
+        assert_eq!(queued.state, JobState::Queued);
+        assert_eq!(daemon.active().len(), 1);
+    }
+}
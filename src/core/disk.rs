@@ -0,0 +1,150 @@
+use std::path::Path;
+use std::time::Duration;
+
+use tokio_util::sync::CancellationToken;
+
+use crate::model::format::MediaFormat;
+use crate::model::video::VideoInfo;
+
+/// How often free space is re-checked while scheduling is paused.
+const RECHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Estimated download size of `video` in `format`, in bytes.
+///
+/// Uses yt-dlp's format metadata when it was fetched, otherwise a typical
+/// bitrate for the format (audio only). Scaled down to the selected sections
+/// or by the removed ones.
+pub fn estimate_size(video: &VideoInfo, format: MediaFormat) -> Option<u64> {
+    let full = match format {
+        MediaFormat::Audio(_) => video.sizes.audio,
+        MediaFormat::Video(_) => video.sizes.video,
+    };
+
+    match (full, video.duration) {
+        (Some(bytes), Some(duration)) if duration > 0.0 => {
+            let fraction = video.download_duration().unwrap_or(duration) / duration;
+            Some((bytes as f64 * fraction.min(1.0)) as u64)
+        }
+        (Some(bytes), _) => Some(bytes),
+        (None, _) => {
+            let kbps = format.approx_bitrate()?;
+            let secs = video.download_duration()?;
+            Some((secs * f64::from(kbps) * 1000.0 / 8.0) as u64)
+        }
+    }
+}
+
+/// Total estimated size of a batch.
+pub struct BatchEstimate {
+    pub bytes: u64,
+    /// Items whose size couldn't be estimated (not included in `bytes`)
+    pub unknown: usize,
+}
+
+pub fn estimate_batch(videos: &[VideoInfo], format: MediaFormat) -> BatchEstimate {
+    let sizes: Vec<Option<u64>> = videos.iter().map(|v| estimate_size(v, format)).collect();
+    BatchEstimate {
+        bytes: sizes.iter().flatten().sum(),
+        unknown: sizes.iter().filter(|s| s.is_none()).count(),
+    }
+}
+
+/// Free space available to unprivileged users on the filesystem holding
+/// `path` (or its nearest existing ancestor, as the output directory may not
+/// exist yet). `None` if it can't be determined.
+#[cfg(unix)]
+pub fn free_space(path: &Path) -> Option<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let existing = path
+        .ancestors()
+        .find(|p| p.is_dir())
+        .unwrap_or(Path::new("."));
+    let c_path = CString::new(existing.as_os_str().as_bytes()).ok()?;
+
+    // SAFETY: statvfs only writes into the struct we pass, and c_path is a
+    // valid NUL-terminated string for the duration of the call.
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    // The field types differ between platforms
+    #[allow(clippy::useless_conversion)]
+    let bytes = u64::from(stat.f_bavail) * u64::from(stat.f_frsize);
+    Some(bytes)
+}
+
+#[cfg(not(unix))]
+pub fn free_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// Wait until `dir` has at least `min_free` bytes available, calling
+/// `on_pause` once if it has to wait. Returns `false` if cancelled meanwhile.
+pub async fn wait_for_space(
+    dir: &Path,
+    min_free: u64,
+    cancel: &CancellationToken,
+    on_pause: impl FnOnce(u64),
+) -> bool {
+    let mut on_pause = Some(on_pause);
+    loop {
+        match free_space(dir) {
+            Some(free) if free < min_free => {
+                if let Some(f) = on_pause.take() {
+                    f(free);
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(RECHECK_INTERVAL) => {}
+                    _ = cancel.cancelled() => return false,
+                }
+            }
+            _ => return true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::format::{AudioCodec, VideoCodec};
+    use crate::model::video::SizeInfo;
+
+    fn video(sizes: SizeInfo) -> VideoInfo {
+        VideoInfo {
+            index: 1,
+            id: "a".into(),
+            title: "A".into(),
+            duration: Some(100.0),
+            sizes,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_estimate_from_metadata() {
+        let mut v = video(SizeInfo {
+            audio: Some(1_000_000),
+            video: Some(50_000_000),
+        });
+        let audio = MediaFormat::Audio(AudioCodec::Opus);
+        assert_eq!(estimate_size(&v, audio), Some(1_000_000));
+        assert_eq!(estimate_size(&v, MediaFormat::Video(VideoCodec::Mp4)), Some(50_000_000));
+
+        // A quarter of the video selected
+        v.selected_sections = vec![(0.0, 25.0)];
+        assert_eq!(estimate_size(&v, audio), Some(250_000));
+    }
+
+    #[test]
+    fn test_estimate_fallback() {
+        let v = video(SizeInfo::default());
+        // 100s at 160 kbit/s
+        assert_eq!(estimate_size(&v, MediaFormat::Audio(AudioCodec::Opus)), Some(2_000_000));
+        assert_eq!(estimate_size(&v, MediaFormat::Video(VideoCodec::Mkv)), None);
+
+        let batch = estimate_batch(&[v.clone(), v], MediaFormat::Video(VideoCodec::Mkv));
+        assert_eq!((batch.bytes, batch.unknown), (0, 2));
+    }
+}
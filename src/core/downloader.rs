@@ -1,56 +1,155 @@
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
+use tokio_util::sync::CancellationToken;
+
+use super::disk;
+use super::failure::FailureKind;
+use super::hooks;
+use super::postprocess::process_audio;
+use super::process::{self, command_line};
+use super::progress::{EventBus, ItemEvents, Phase, ProgressEvent};
+use super::queue::{DownloadQueue, EntryState};
+use super::throttle::{split_rate, StartSpacer};
 
 use crate::error::{AppError, Result};
 use crate::model::config::DownloadConfig;
 use crate::model::format::{AudioCodec, MediaFormat, VideoCodec};
+use crate::model::output::{format_size, OutputFile};
 use crate::model::video::VideoInfo;
 
 pub struct DownloadResult {
     pub title: String,
     pub success: bool,
+    /// Interrupted (or never started) because the batch was cancelled
+    pub cancelled: bool,
     pub error: Option<String>,
+    /// Category of `error`, when it could be recognized
+    pub kind: Option<FailureKind>,
+    /// Files written, in the order yt-dlp finished them
+    pub files: Vec<OutputFile>,
+    /// The after-item hook failed (the download itself succeeded)
+    pub hook_error: Option<String>,
 }
 
+impl DownloadResult {
+    fn failed(title: &str, error: String, kind: Option<FailureKind>) -> Self {
+        Self {
+            title: title.to_string(),
+            success: false,
+            cancelled: false,
+            error: Some(error),
+            kind,
+            files: Vec::new(),
+            hook_error: None,
+        }
+    }
+
+    fn cancelled(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            success: false,
+            cancelled: true,
+            error: None,
+            kind: None,
+            files: Vec::new(),
+            hook_error: None,
+        }
+    }
+}
+
+/// Prefix of the line yt-dlp prints for each finished file, so it can be told
+/// apart from progress output.
+const FILE_MARKER: &str = "yt-grab-file:";
+
+/// Minimum time between two progress lines in the `-vv` log.
+const PROGRESS_LOG_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Downloads a single video. Implementations must stop (and clean up any
+/// child process) when `cancel` fires, returning `AppError::Cancelled`.
 pub trait Downloader {
     fn download(
         &self,
         video: &VideoInfo,
         config: &DownloadConfig,
-        progress_cb: Box<dyn Fn(&str) + Send>,
+        events: &ItemEvents,
+        cancel: &CancellationToken,
     ) -> impl std::future::Future<Output = Result<DownloadResult>> + Send;
 }
 
-pub struct YtDlpDownloader;
+pub struct YtDlpDownloader {
+    /// Per-process rate in bytes/s (`download_all` splits the global budget)
+    pub rate_limit: Option<u64>,
+}
 
 impl YtDlpDownloader {
-    fn build_args(video: &VideoInfo, config: &DownloadConfig) -> Vec<String> {
+    /// The downloader `download_all` uses for a batch of `count` items, with
+    /// the global rate limit split across the parallel downloads.
+    pub fn for_batch(config: &DownloadConfig, count: usize) -> Self {
+        Self {
+            rate_limit: config
+                .rate_limit
+                .map(|budget| split_rate(budget, config.concurrency, count)),
+        }
+    }
+
+    /// The yt-dlp arguments used to download `video`.
+    pub fn build_args(&self, video: &VideoInfo, config: &DownloadConfig) -> Vec<String> {
         let mut args = vec![
             "--no-warnings".to_string(),
             "--newline".to_string(),
             "--progress".to_string(),
-            "-o".to_string(),
         ];
 
-        // Use section_title in filename when downloading specific sections
+        if let Some(rate) = self.rate_limit {
+            args.push("--limit-rate".to_string());
+            args.push(rate.to_string());
+        }
+        if let Some(sleep) = config.sleep_requests {
+            args.push("--sleep-requests".to_string());
+            args.push(format!("{:.2}", sleep.as_secs_f64()));
+        }
+
+        args.extend(config.network.yt_dlp_args());
+
+        // Report each final file (after conversion and moving); `--progress`
+        // keeps progress lines coming despite the quiet mode `--print` implies
+        args.push("--print".to_string());
+        args.push(format!(
+            "after_move:{FILE_MARKER}%(section_start)s\t%(section_end)s\t%(duration)s\t%(filepath)s"
+        ));
+        args.push("-o".to_string());
+
+        // yt-dlp has no section title for time ranges, so sections are told
+        // apart by where they start (see `expected_files`)
         let template = if video.has_selected_sections() {
-            "%(title)s - %(section_title)s.%(ext)s"
+            "%(title)s - %(section_start>%H-%M-%S)s.%(ext)s"
         } else {
             "%(title)s.%(ext)s"
         };
         let output_template = config.output_dir.join(template).to_string_lossy().to_string();
         args.push(output_template);
+        if config.write_info_json {
+            // One per video, named after it even when downloading sections
+            let template = config.output_dir.join("%(title)s.%(ext)s");
+            args.push("--write-info-json".to_string());
+            args.push("-o".to_string());
+            args.push(format!("infojson:{}", template.to_string_lossy()));
+        }
 
         // Add --download-sections for each selected chapter
         for (start, end) in &video.selected_sections {
-            let start_m = *start as u64 / 60;
-            let start_s = *start as u64 % 60;
-            let end_m = *end as u64 / 60;
-            let end_s = *end as u64 % 60;
             args.push("--download-sections".to_string());
-            args.push(format!("*{start_m:02}:{start_s:02}-{end_m:02}:{end_s:02}"));
+            args.push(time_range(*start, *end));
+        }
+        // Cut ranges out of a full video, keeping it one file
+        for (start, end) in &video.removed_sections {
+            args.push("--remove-chapters".to_string());
+            args.push(time_range(*start, *end));
         }
 
         match config.format {
@@ -84,9 +183,10 @@ impl Downloader for YtDlpDownloader {
         &self,
         video: &VideoInfo,
         config: &DownloadConfig,
-        progress_cb: Box<dyn Fn(&str) + Send>,
+        events: &ItemEvents,
+        cancel: &CancellationToken,
     ) -> Result<DownloadResult> {
-        let args = Self::build_args(video, config);
+        let args = self.build_args(video, config);
 
         // Ensure output directory exists
         let dir = &config.output_dir;
@@ -94,21 +194,39 @@ impl Downloader for YtDlpDownloader {
             tokio::fs::create_dir_all(dir).await?;
         }
 
-        let mut child = Command::new("yt-dlp")
+        log::info!(
+            "Downloading \"{}\": {}",
+            video.title,
+            config.network.redact(&command_line("yt-dlp", &args))
+        );
+        let started = Instant::now();
+        let mut child = process::make_cancellable(&mut Command::new("yt-dlp"))
             .args(&args)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
-            .spawn()?;
+            .spawn()
+            .map_err(|e| process::spawn_error("yt-dlp", e))?;
+        let pid = child.id();
+        let _group = process::track(pid);
 
         // Read stdout and stderr concurrently
         let stderr_handle = {
             let stderr = child.stderr.take();
+            let title = video.title.clone();
+            let network = config.network.clone();
+            let events = events.clone();
             tokio::spawn(async move {
                 let mut err_lines = Vec::new();
                 if let Some(stderr) = stderr {
                     let reader = BufReader::new(stderr);
                     let mut lines = reader.lines();
                     while let Ok(Some(line)) = lines.next_line().await {
+                        // yt-dlp retries failed requests and fragments itself
+                        if line.contains("Retrying") {
+                            let line = network.redact(&line);
+                            log::info!("\"{title}\": {line}");
+                            events.retry(line);
+                        }
                         err_lines.push(line);
                     }
                 }
@@ -116,38 +234,136 @@ impl Downloader for YtDlpDownloader {
             })
         };
 
-        if let Some(stdout) = child.stdout.take() {
-            let reader = BufReader::new(stdout);
-            let mut lines = reader.lines();
-            while let Ok(Some(line)) = lines.next_line().await {
-                progress_cb(&line);
+        let stdout = child.stdout.take();
+        let work = async {
+            let mut files = Vec::new();
+            let mut converting = false;
+            let mut last_logged: Option<Instant> = None;
+            if let Some(stdout) = stdout {
+                let reader = BufReader::new(stdout);
+                let mut lines = reader.lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    if let Some(file) = parse_output_line(&line) {
+                        files.push(file);
+                        continue;
+                    }
+                    if let Some(percent) = parse_progress_percent(&line) {
+                        // Progress lines come many times a second
+                        if last_logged.is_none_or(|t| t.elapsed() >= PROGRESS_LOG_INTERVAL) {
+                            log::debug!("{line}");
+                            last_logged = Some(Instant::now());
+                        }
+                        // Sections are downloaded and converted one by one
+                        converting = false;
+                        events.progress(percent);
+                    } else {
+                        log::debug!("{line}");
+                        if !converting && is_conversion_line(&line) {
+                            converting = true;
+                            events.phase(Phase::Converting);
+                        }
+                    }
+                }
             }
-        }
+            (child.wait().await, files)
+        };
+        tokio::pin!(work);
 
-        let status = child.wait().await?;
+        let (status, mut files) = tokio::select! {
+            (status, files) = &mut work => (status?, files),
+            _ = cancel.cancelled() => {
+                // Give yt-dlp a chance to stop its ffmpeg children, then force it
+                process::terminate(pid);
+                if tokio::time::timeout(process::TERMINATE_TIMEOUT, &mut work).await.is_err() {
+                    process::kill(pid);
+                }
+                return Err(AppError::Cancelled);
+            }
+        };
         let stderr_lines = stderr_handle.await.unwrap_or_default();
+        log::info!(
+            "yt-dlp finished ({status}) in {:.1?} for \"{}\"",
+            started.elapsed(),
+            video.title
+        );
+        if !stderr_lines.is_empty() {
+            log::debug!(
+                "yt-dlp stderr for \"{}\":\n{}",
+                video.title,
+                config.network.redact(&stderr_lines.join("\n"))
+            );
+        }
 
         if status.success() {
+            for file in &mut files {
+                file.size = tokio::fs::metadata(&file.path).await.ok().map(|m| m.len());
+            }
             Ok(DownloadResult {
                 title: video.title.clone(),
                 success: true,
+                cancelled: false,
                 error: None,
+                kind: None,
+                files,
+                hook_error: None,
             })
         } else {
             // Extract the most useful error line from stderr
             let reason = stderr_lines
                 .iter()
                 .rfind(|l| l.starts_with("ERROR:"))
-                .cloned()
+                .map(|l| config.network.redact(l))
                 .unwrap_or_else(|| "yt-dlp exited with non-zero status".to_string());
             Err(AppError::DownloadFailed {
                 title: video.title.clone(),
+                kind: FailureKind::classify(&stderr_lines.join("\n")),
                 reason,
             })
         }
     }
 }
 
+/// A `*MM:SS-MM:SS` range for `--download-sections` and `--remove-chapters`,
+/// in whole seconds.
+fn time_range(start: f64, end: f64) -> String {
+    let (start, end) = (start as u64, end as u64);
+    format!("*{:02}:{:02}-{:02}:{:02}", start / 60, start % 60, end / 60, end % 60)
+}
+
+/// The files a download is expected to produce, following the output
+/// template: one per selected section, named after the (whole) second it
+/// starts at. yt-dlp's own filename sanitizing may differ in details.
+pub fn expected_files(video: &VideoInfo, config: &DownloadConfig) -> Vec<PathBuf> {
+    let title = video.title.replace('/', "⧸");
+    let ext = config.format.extension();
+    if !video.has_selected_sections() {
+        return vec![config.output_dir.join(format!("{title}.{ext}"))];
+    }
+
+    video
+        .selected_sections
+        .iter()
+        .map(|(start, _)| {
+            let start = *start as u64;
+            let section = format!("{:02}-{:02}-{:02}", start / 3600, start / 60 % 60, start % 60);
+            config.output_dir.join(format!("{title} - {section}.{ext}"))
+        })
+        .collect()
+}
+
+/// A line from one of yt-dlp's post-processors, which run after the download.
+fn is_conversion_line(line: &str) -> bool {
+    const PREFIXES: &[&str] = &[
+        "[ExtractAudio]",
+        "[Merger]",
+        "[VideoConvertor]",
+        "[VideoRemuxer]",
+        "[FixupM3u8]",
+        "[ModifyChapters]",
+    ];
+    PREFIXES.iter().any(|p| line.starts_with(p))
+}
+
 /// Extract a percentage from yt-dlp progress output lines.
 /// Lines look like: "[download]  45.2% of 5.23MiB at 1.2MiB/s ETA 00:03"
 pub fn parse_progress_percent(line: &str) -> Option<f64> {
@@ -166,85 +382,347 @@ pub fn parse_progress_percent(line: &str) -> Option<f64> {
     None
 }
 
+/// Parse the line printed for a finished file (see `FILE_MARKER`). The
+/// duration is the section length when downloading sections.
+pub fn parse_output_line(line: &str) -> Option<OutputFile> {
+    let rest = line.strip_prefix(FILE_MARKER)?;
+    let mut fields = rest.splitn(4, '\t');
+    let mut number = || fields.next().and_then(|f| f.parse::<f64>().ok());
+    let (start, end, duration) = (number(), number(), number());
+    let path = fields.next().filter(|p| !p.is_empty())?;
+
+    let duration = match (start, end) {
+        (Some(start), Some(end)) if end > start => Some(end - start),
+        _ => duration,
+    };
+    Some(OutputFile {
+        path: PathBuf::from(path),
+        size: None,
+        duration,
+    })
+}
+
+/// Download `videos`, `config.concurrency` at a time, in `config.order`.
 pub async fn download_all(
     videos: &[VideoInfo],
     config: &DownloadConfig,
-    multi_progress: &indicatif::MultiProgress,
+    events: &EventBus,
+    cancel: CancellationToken,
 ) -> Vec<DownloadResult> {
-    use indicatif::{ProgressBar, ProgressStyle};
-    use tokio::sync::Semaphore;
+    let queue = DownloadQueue::new(config.order, events.clone());
+    for video in videos {
+        queue.push(video.clone(), 0);
+    }
+    queue.close();
+    download_queue(&queue, config, events, cancel).await
+}
 
-    let semaphore = std::sync::Arc::new(Semaphore::new(config.concurrency));
-    let config = std::sync::Arc::new(config.clone());
-    let mut handles = Vec::new();
+/// Download the items of `queue` as they become ready, `config.concurrency`
+/// at a time, until it is closed and drained or `cancel` fires. Results are
+/// in item order.
+pub async fn download_queue(
+    queue: &DownloadQueue,
+    config: &DownloadConfig,
+    events: &EventBus,
+    cancel: CancellationToken,
+) -> Vec<DownloadResult> {
+    // --fail-fast stops the batch without cancelling the caller's token, so
+    // callers can still tell a user interrupt apart
+    let cancel = cancel.child_token();
+    // Items added later could otherwise push the total over the rate limit
+    let count = if queue.is_closed() {
+        queue.entries().len()
+    } else {
+        config.concurrency
+    };
+    let downloader = Arc::new(YtDlpDownloader::for_batch(config, count));
+    let spacer = Arc::new(config.sleep_interval.map(StartSpacer::new));
+    let config = Arc::new(config.clone());
 
-    let style = ProgressStyle::with_template(
-        "  {prefix:.cyan} [{bar:30.green/dim}] {msg}",
-    )
-    .unwrap()
-    .progress_chars("━╸─");
+    let workers: Vec<_> = (0..config.concurrency)
+        .map(|_| {
+            let queue = queue.clone();
+            let config = config.clone();
+            let events = events.clone();
+            let cancel = cancel.clone();
+            let downloader = downloader.clone();
+            let spacer = spacer.clone();
+            tokio::spawn(async move {
+                let mut results = Vec::new();
+                while let Some((item, video, item_cancel)) = queue.next(&cancel).await {
+                    let result = download_item(
+                        item,
+                        &video,
+                        &config,
+                        &downloader,
+                        spacer.as_ref().as_ref(),
+                        &events,
+                        &item_cancel,
+                    )
+                    .await;
+                    let state = match &result {
+                        Ok(_) => EntryState::Done,
+                        Err(AppError::Cancelled) => EntryState::Cancelled,
+                        Err(_) => EntryState::Failed,
+                    };
+                    // Paused while running: it will start again later
+                    if queue.finish(item, state) {
+                        continue;
+                    }
 
-    for video in videos {
-        let permit = semaphore.clone();
-        let config = config.clone();
-        let video = video.clone();
-        let pb = multi_progress.add(ProgressBar::new(100));
-        pb.set_style(style.clone());
-        let title_short = if video.title.len() > 40 {
-            format!("{}...", &video.title[..37])
-        } else {
-            video.title.clone()
-        };
-        pb.set_prefix(title_short);
-        pb.set_message("waiting...");
-
-        let handle = tokio::spawn(async move {
-            let _permit = permit.acquire().await.unwrap();
-            pb.set_message("downloading");
-
-            let pb_clone = pb.clone();
-            let downloader = YtDlpDownloader;
-            let result = downloader
-                .download(
-                    &video,
-                    &config,
-                    Box::new(move |line: &str| {
-                        if let Some(pct) = parse_progress_percent(line) {
-                            pb_clone.set_position(pct as u64);
+                    let result = match result {
+                        Ok(r) => {
+                            events.send(ProgressEvent::Finished { item });
+                            r
+                        }
+                        Err(AppError::Cancelled) => {
+                            events.send(ProgressEvent::Cancelled { item });
+                            DownloadResult::cancelled(&video.title)
+                        }
+                        Err(e) => {
+                            events.send(ProgressEvent::Failed {
+                                item,
+                                error: e.to_string(),
+                            });
+                            if config.fail_fast {
+                                log::warn!(
+                                    "\"{}\" failed, cancelling the rest of the batch (--fail-fast)",
+                                    video.title
+                                );
+                                cancel.cancel();
+                            }
+                            DownloadResult::failed(&video.title, e.to_string(), e.failure_kind())
                         }
-                    }),
-                )
-                .await;
-
-            match result {
-                Ok(r) => {
-                    pb.set_position(100);
-                    pb.finish_with_message("done ✓");
-                    r
+                    };
+                    results.push((item, result));
                 }
+                results
+            })
+        })
+        .collect();
+
+    let mut results = Vec::new();
+    for worker in workers {
+        match worker.await {
+            Ok(r) => results.extend(r),
+            Err(e) => log::error!("Download worker failed: {e}"),
+        }
+    }
+    // Never started, because the batch was cancelled (or a worker died)
+    for (item, title) in queue.cancel_pending() {
+        results.push((item, DownloadResult::cancelled(&title)));
+    }
+    results.sort_by_key(|(item, _)| *item);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+/// Wait for disk space and the start interval, then download, post-process
+/// and run the after-item hook.
+async fn download_item(
+    item: usize,
+    video: &VideoInfo,
+    config: &DownloadConfig,
+    downloader: &YtDlpDownloader,
+    spacer: Option<&StartSpacer>,
+    events: &EventBus,
+    cancel: &CancellationToken,
+) -> Result<DownloadResult> {
+    if let Some(min_free) = config.min_free_space {
+        let has_space = disk::wait_for_space(&config.output_dir, min_free, cancel, |free| {
+            log::info!(
+                "Low disk space ({} free), holding \"{}\"",
+                format_size(free),
+                video.title
+            );
+            events.send(ProgressEvent::Paused {
+                item,
+                reason: format!("low disk space ({} free)", format_size(free)),
+            });
+        })
+        .await;
+        if !has_space {
+            return Err(AppError::Cancelled);
+        }
+    }
+    if let Some(spacer) = spacer {
+        tokio::select! {
+            _ = spacer.wait() => {}
+            _ = cancel.cancelled() => return Err(AppError::Cancelled),
+        }
+    }
+    events.send(ProgressEvent::Started { item });
+
+    let item_events = events.item(item);
+    let result = downloader.download(video, config, &item_events, cancel).await;
+
+    let result = match result {
+        Ok(r) if config.format.is_audio() && !config.audio.is_empty() => {
+            item_events.phase(Phase::Processing);
+            process_files(r, video, config, &item_events, cancel).await
+        }
+        result => result,
+    };
+
+    // Hook failures are recorded but don't fail the item
+    match (result, &config.hooks.after_item) {
+        (Ok(mut r), Some(hook)) => {
+            match hooks::run_item_hooks(hook, video, &r.files, config, cancel).await {
+                Ok(()) => Ok(r),
+                Err(AppError::Cancelled) => Err(AppError::Cancelled),
                 Err(e) => {
-                    pb.finish_with_message("FAILED ✗");
-                    DownloadResult {
-                        title: video.title.clone(),
-                        success: false,
-                        error: Some(e.to_string()),
-                    }
+                    r.hook_error = Some(e.to_string());
+                    Ok(r)
                 }
             }
-        });
-        handles.push(handle);
+        }
+        (result, _) => result,
     }
+}
 
-    let mut results = Vec::new();
-    for handle in handles {
-        match handle.await {
-            Ok(r) => results.push(r),
-            Err(e) => results.push(DownloadResult {
-                title: "unknown".to_string(),
-                success: false,
-                error: Some(e.to_string()),
-            }),
+/// Run the audio post-processing stage over every file of a finished item.
+async fn process_files(
+    mut result: DownloadResult,
+    video: &VideoInfo,
+    config: &DownloadConfig,
+    events: &ItemEvents,
+    cancel: &CancellationToken,
+) -> Result<DownloadResult> {
+    let is_clip = video.has_selected_sections();
+    let count = result.files.len().max(1) as f64;
+    for (i, file) in result.files.iter_mut().enumerate() {
+        // Spread the percentage over all files of the item
+        let on_progress = |percent: f64| events.progress((i as f64 * 100.0 + percent) / count);
+        process_audio(file, &config.audio, is_clip, &on_progress, cancel).await?;
+    }
+    Ok(result)
+}
+
+/// Is `name` one of yt-dlp's temporary files for the output named `stem`:
+/// `<stem>.<...>.part`, `.ytdl`, `.part-Frag<n>` or `<stem>.temp.<ext>`?
+fn is_partial_of(name: &str, stem: &str) -> bool {
+    let Some(rest) = name.strip_prefix(stem).and_then(|r| r.strip_prefix('.')) else {
+        return false;
+    };
+    let rest = format!(".{rest}");
+    rest.ends_with(".part")
+        || rest.ends_with(".ytdl")
+        || rest.contains(".part-Frag")
+        || rest
+            .strip_prefix(".temp.")
+            .is_some_and(|ext| !ext.is_empty() && !ext.contains('.'))
+}
+
+/// Delete yt-dlp's partial files in `dir` for the `expected` outputs (see
+/// `expected_files`) that were modified since `since`. Returns the removed
+/// paths.
+pub fn remove_partial_files(
+    dir: &Path,
+    expected: &[PathBuf],
+    since: SystemTime,
+) -> std::io::Result<Vec<PathBuf>> {
+    let stems: Vec<String> = expected
+        .iter()
+        .filter_map(|p| p.file_stem())
+        .map(|s| s.to_string_lossy().into_owned())
+        .collect();
+    let mut removed = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if !stems.iter().any(|stem| is_partial_of(&name, stem)) {
+            continue;
+        }
+
+        let recent = std::fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .is_ok_and(|modified| modified >= since);
+        if recent && std::fs::remove_file(&path).is_ok() {
+            removed.push(path);
         }
     }
-    results
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_output_line() {
+        let file = parse_output_line("yt-grab-file:NA\tNA\t212.0\t/music/Song.mp3").unwrap();
+        assert_eq!(file.path, PathBuf::from("/music/Song.mp3"));
+        assert_eq!(file.duration, Some(212.0));
+
+        // Sections report their own length; tabs in paths survive
+        let file = parse_output_line("yt-grab-file:60.0\t90.5\t212.0\t/a\tb.mp3").unwrap();
+        assert_eq!(file.path, PathBuf::from("/a\tb.mp3"));
+        assert_eq!(file.duration, Some(30.5));
+
+        assert!(parse_output_line("[download]  45.2% of 5.23MiB").is_none());
+    }
+
+    #[test]
+    fn test_is_conversion_line() {
+        assert!(is_conversion_line("[ExtractAudio] Destination: Song.mp3"));
+        assert!(is_conversion_line("[Merger] Merging formats into \"Clip.mkv\""));
+        assert!(!is_conversion_line("[download]  45.2% of 5.23MiB at 1.2MiB/s ETA 00:03"));
+        assert!(!is_conversion_line("Deleting original file Song.webm [ExtractAudio]"));
+    }
+
+    #[test]
+    fn test_is_partial_of() {
+        assert!(is_partial_of("Song.webm.part", "Song"));
+        assert!(is_partial_of("Song.f140.m4a.part", "Song"));
+        assert!(is_partial_of("Song.webm.ytdl", "Song"));
+        assert!(is_partial_of("Song.f251.webm.part-Frag3", "Song"));
+        assert!(is_partial_of("Song.temp.mp3", "Song"));
+        // Other titles, finished files and look-alikes are left alone
+        assert!(!is_partial_of("Song 2.webm.part", "Song"));
+        assert!(!is_partial_of("Song.mp3", "Song"));
+        assert!(!is_partial_of("Song.temp.notes.txt", "Song"));
+        assert!(!is_partial_of("Other.temp.mp3", "Song"));
+    }
+    #[test]
+    fn test_section_files() {
+        let config = crate::core::grabber::Grabber::builder()
+            .format(MediaFormat::Audio(AudioCodec::Mp3))
+            .output_dir(PathBuf::from("out"))
+            .build()
+            .config()
+            .clone();
+        let downloader = YtDlpDownloader { rate_limit: None };
+        let mut video = VideoInfo {
+            title: "Talk".into(),
+            url: "https://youtu.be/abc".into(),
+            ..Default::default()
+        };
+
+        // Sections get a file each, named after where they start
+        video.selected_sections = vec![(10.0, 50.5), (3725.0, 3800.0)];
+        let args = downloader.build_args(&video, &config);
+        let template = Path::new("out").join("%(title)s - %(section_start>%H-%M-%S)s.%(ext)s");
+        assert!(args.contains(&template.to_string_lossy().to_string()));
+        assert!(args.contains(&"*00:10-00:50".to_string()));
+        assert!(args.contains(&"*62:05-63:20".to_string()));
+        assert_eq!(
+            expected_files(&video, &config),
+            vec![
+                Path::new("out").join("Talk - 00-00-10.mp3"),
+                Path::new("out").join("Talk - 01-02-05.mp3"),
+            ]
+        );
+
+        // Removed ranges are cut out of a single file
+        video.selected_sections.clear();
+        video.removed_sections = vec![(0.0, 10.0), (50.0, 60.0)];
+        let args = downloader.build_args(&video, &config);
+        let cuts: Vec<&str> = args
+            .windows(2)
+            .filter(|w| w[0] == "--remove-chapters")
+            .map(|w| w[1].as_str())
+            .collect();
+        assert_eq!(cuts, ["*00:00-00:10", "*00:50-01:00"]);
+        assert!(!args.iter().any(|a| a == "--download-sections"));
+        assert_eq!(expected_files(&video, &config), vec![Path::new("out").join("Talk.mp3")]);
+    }
 }
@@ -0,0 +1,362 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use super::downloader::DownloadResult;
+
+use crate::error::Result;
+use crate::model::video::VideoInfo;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    M3u8,
+    Xspf,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::M3u8 => "m3u8",
+            Self::Xspf => "xspf",
+            Self::Json => "json",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "m3u8" | "m3u" => Ok(Self::M3u8),
+            "xspf" => Ok(Self::Xspf),
+            "json" => Ok(Self::Json),
+            other => Err(format!(
+                "unknown export format \"{other}\" (expected m3u8, xspf or json)"
+            )),
+        }
+    }
+}
+
+/// Parse a comma-separated list of export formats.
+pub fn parse_formats(input: &str) -> std::result::Result<Vec<ExportFormat>, String> {
+    input.split(',').map(str::parse).collect()
+}
+
+/// A playlist item to export, with its download outcome if it was downloaded.
+pub struct ExportItem<'a> {
+    pub video: &'a VideoInfo,
+    pub result: Option<&'a DownloadResult>,
+}
+
+/// One playable entry: a downloaded file, or the video URL when nothing was
+/// downloaded (the `export` command).
+struct Track {
+    title: String,
+    location: String,
+    duration: Option<f64>,
+}
+
+fn tracks(items: &[ExportItem], base: &Path) -> Vec<Track> {
+    let mut tracks = Vec::new();
+    for item in items {
+        let video = item.video;
+        let Some(result) = item.result else {
+            tracks.push(Track {
+                title: video.title.clone(),
+                location: video.url.clone(),
+                duration: video.duration,
+            });
+            continue;
+        };
+
+        // Sections have their own names in the file name
+        let multiple = result.files.len() > 1;
+        for file in &result.files {
+            let title = match file.path.file_stem() {
+                Some(stem) if multiple => stem.to_string_lossy().to_string(),
+                _ => video.title.clone(),
+            };
+            let path = file.path.strip_prefix(base).unwrap_or(&file.path);
+            tracks.push(Track {
+                title,
+                location: path.to_string_lossy().to_string(),
+                duration: file.duration.or(video.duration),
+            });
+        }
+    }
+    tracks
+}
+
+fn render_m3u8(tracks: &[Track]) -> String {
+    let mut out = String::from("#EXTM3U\n");
+    for track in tracks {
+        let secs = track.duration.map_or(-1, |d| d.round() as i64);
+        out.push_str(&format!("#EXTINF:{secs},{}\n{}\n", track.title, track.location));
+    }
+    out
+}
+
+fn render_xspf(title: Option<&str>, tracks: &[Track]) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n",
+    );
+    if let Some(title) = title {
+        out.push_str(&format!("  <title>{}</title>\n", xml_escape(title)));
+    }
+    out.push_str("  <trackList>\n");
+    for track in tracks {
+        out.push_str("    <track>\n");
+        out.push_str(&format!(
+            "      <location>{}</location>\n",
+            xml_escape(&uri_encode(&track.location))
+        ));
+        out.push_str(&format!("      <title>{}</title>\n", xml_escape(&track.title)));
+        if let Some(secs) = track.duration {
+            out.push_str(&format!("      <duration>{}</duration>\n", (secs * 1000.0).round() as u64));
+        }
+        out.push_str("    </track>\n");
+    }
+    out.push_str("  </trackList>\n</playlist>\n");
+    out
+}
+
+#[derive(Serialize)]
+struct Manifest<'a> {
+    title: Option<&'a str>,
+    source: &'a str,
+    items: Vec<ManifestItem<'a>>,
+}
+
+#[derive(Serialize)]
+struct ManifestItem<'a> {
+    index: usize,
+    title: &'a str,
+    id: &'a str,
+    url: &'a str,
+    duration: Option<f64>,
+    upload_date: Option<&'a str>,
+    chapters: Vec<ManifestChapter<'a>>,
+    /// "downloaded", "failed", "cancelled", or "not downloaded"
+    status: &'static str,
+    error: Option<&'a str>,
+    /// Failure category, e.g. "geo-blocked"
+    failure: Option<String>,
+    files: Vec<ManifestFile<'a>>,
+}
+
+#[derive(Serialize)]
+struct ManifestChapter<'a> {
+    title: &'a str,
+    start: f64,
+    end: f64,
+}
+
+#[derive(Serialize)]
+struct ManifestFile<'a> {
+    path: &'a Path,
+    size: Option<u64>,
+    duration: Option<f64>,
+}
+
+fn render_json(title: Option<&str>, source: &str, items: &[ExportItem]) -> Result<String> {
+    let manifest = Manifest {
+        title,
+        source,
+        items: items
+            .iter()
+            .map(|item| {
+                let video = item.video;
+                let status = match item.result {
+                    None => "not downloaded",
+                    Some(r) if r.success => "downloaded",
+                    Some(r) if r.cancelled => "cancelled",
+                    Some(_) => "failed",
+                };
+                ManifestItem {
+                    index: video.index,
+                    title: &video.title,
+                    id: &video.id,
+                    url: &video.url,
+                    duration: video.duration,
+                    upload_date: video.upload_date.as_deref(),
+                    chapters: video
+                        .chapters
+                        .iter()
+                        .map(|c| ManifestChapter {
+                            title: &c.title,
+                            start: c.start_time,
+                            end: c.end_time,
+                        })
+                        .collect(),
+                    status,
+                    error: item.result.and_then(|r| r.error.as_deref()),
+                    failure: item.result.and_then(|r| r.kind).map(|k| k.to_string()),
+                    files: item
+                        .result
+                        .map(|r| {
+                            r.files
+                                .iter()
+                                .map(|f| ManifestFile {
+                                    path: &f.path,
+                                    size: f.size,
+                                    duration: f.duration,
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                }
+            })
+            .collect(),
+    };
+    Ok(serde_json::to_string_pretty(&manifest)? + "\n")
+}
+
+/// Render a playlist file. File locations in M3U8/XSPF are made relative to
+/// `base`, the directory the playlist is written to.
+pub fn render(
+    format: ExportFormat,
+    title: Option<&str>,
+    source: &str,
+    items: &[ExportItem],
+    base: &Path,
+) -> Result<String> {
+    match format {
+        ExportFormat::M3u8 => Ok(render_m3u8(&tracks(items, base))),
+        ExportFormat::Xspf => Ok(render_xspf(title, &tracks(items, base))),
+        ExportFormat::Json => render_json(title, source, items),
+    }
+}
+
+/// Write each format to `<dir>/<playlist title>.<ext>`. Returns the paths.
+pub fn write_exports(
+    formats: &[ExportFormat],
+    dir: &Path,
+    title: Option<&str>,
+    source: &str,
+    items: &[ExportItem],
+) -> Result<Vec<PathBuf>> {
+    let name = file_name(title.unwrap_or("playlist"));
+    let mut written = Vec::new();
+    for format in formats {
+        let path = dir.join(format!("{name}.{}", format.extension()));
+        std::fs::write(&path, render(*format, title, source, items, dir)?)?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Replace characters that are not allowed in file names.
+fn file_name(title: &str) -> String {
+    let name: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    match name.trim() {
+        "" => "playlist".to_string(),
+        name => name.to_string(),
+    }
+}
+
+pub(crate) fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Percent-encode a relative path for use as a URI; URLs are left alone.
+pub(crate) fn uri_encode(location: &str) -> String {
+    if location.contains("://") {
+        return location.to_string();
+    }
+    let mut out = String::new();
+    for byte in location.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                out.push(byte as char)
+            }
+            _ => out.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::output::OutputFile;
+
+    fn video() -> VideoInfo {
+        VideoInfo {
+            index: 1,
+            id: "abc".into(),
+            title: "Rock & Roll".into(),
+            duration: Some(125.4),
+            url: "https://www.youtube.com/watch?v=abc".into(),
+            ..Default::default()
+        }
+    }
+
+    fn downloaded(path: &str) -> DownloadResult {
+        DownloadResult {
+            title: "Rock & Roll".into(),
+            success: true,
+            cancelled: false,
+            error: None,
+            kind: None,
+            files: vec![OutputFile {
+                path: PathBuf::from(path),
+                size: Some(1000),
+                duration: Some(125.4),
+            }],
+            hook_error: None,
+        }
+    }
+
+    #[test]
+    fn test_m3u8_relative_paths() {
+        let video = video();
+        let result = downloaded("/music/Rock & Roll.mp3");
+        let items = [ExportItem { video: &video, result: Some(&result) }];
+        let out = render(ExportFormat::M3u8, None, "", &items, Path::new("/music")).unwrap();
+        assert_eq!(out, "#EXTM3U\n#EXTINF:125,Rock & Roll\nRock & Roll.mp3\n");
+    }
+
+    #[test]
+    fn test_xspf_escapes() {
+        let video = video();
+        let result = downloaded("/music/Rock & Roll.mp3");
+        let items = [ExportItem { video: &video, result: Some(&result) }];
+        let out = render(ExportFormat::Xspf, Some("Mix"), "", &items, Path::new("/music")).unwrap();
+        assert!(out.contains("<location>Rock%20%26%20Roll.mp3</location>"));
+        assert!(out.contains("<title>Rock &amp; Roll</title>"));
+        assert!(out.contains("<duration>125400</duration>"));
+    }
+
+    #[test]
+    fn test_not_downloaded_uses_url() {
+        let video = video();
+        let items = [ExportItem { video: &video, result: None }];
+        let out = render(ExportFormat::M3u8, None, "", &items, Path::new(".")).unwrap();
+        assert!(out.contains("https://www.youtube.com/watch?v=abc"));
+        let json = render(ExportFormat::Json, None, "", &items, Path::new(".")).unwrap();
+        assert!(json.contains("\"status\": \"not downloaded\""));
+    }
+
+    #[test]
+    fn test_parse_formats() {
+        assert_eq!(
+            parse_formats("m3u8,json").unwrap(),
+            vec![ExportFormat::M3u8, ExportFormat::Json]
+        );
+        assert!(parse_formats("pls").is_err());
+    }
+}
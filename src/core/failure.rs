@@ -0,0 +1,170 @@
+use std::fmt;
+
+/// What went wrong in a failed yt-dlp run, recognized from its stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FailureKind {
+    Unavailable,
+    Private,
+    GeoBlocked,
+    AgeRestricted,
+    MembersOnly,
+    RateLimited,
+    Network,
+    FfmpegMissing,
+    DiskFull,
+    UnsupportedUrl,
+    Other,
+}
+
+/// Lowercase stderr fragments for each kind, checked in order: more specific
+/// messages come first (a geo block also says "not available").
+const PATTERNS: &[(FailureKind, &[&str])] = &[
+    (
+        FailureKind::MembersOnly,
+        &["members-only", "members only", "join this channel", "channel's members"],
+    ),
+    (
+        FailureKind::AgeRestricted,
+        &["confirm your age", "age-restricted", "age restricted", "inappropriate for some users"],
+    ),
+    (
+        FailureKind::GeoBlocked,
+        &["in your country", "geo restriction", "geo-restrict", "geoblock"],
+    ),
+    (FailureKind::Private, &["private video", "this video is private", "playlist is private"]),
+    (
+        FailureKind::RateLimited,
+        &["http error 429", "too many requests", "rate-limit", "rate limit", "not a bot"],
+    ),
+    (
+        FailureKind::FfmpegMissing,
+        &["ffmpeg not found", "ffprobe and ffmpeg not found", "ffmpeg is not installed"],
+    ),
+    (FailureKind::DiskFull, &["no space left on device", "errno 28", "disk full"]),
+    (FailureKind::UnsupportedUrl, &["unsupported url", "is not a valid url"]),
+    (
+        FailureKind::Unavailable,
+        &[
+            "video unavailable",
+            "video is unavailable",
+            "no longer available",
+            "has been removed",
+            "does not exist",
+            "account associated with this video has been terminated",
+        ],
+    ),
+    (
+        FailureKind::Network,
+        &[
+            "unable to download webpage",
+            "connection refused",
+            "connection reset",
+            "timed out",
+            "name or service not known",
+            "temporary failure in name resolution",
+            "network is unreachable",
+            "getaddrinfo failed",
+            "unable to connect to proxy",
+        ],
+    ),
+];
+
+impl FailureKind {
+    pub fn classify(stderr: &str) -> Self {
+        let text = stderr.to_lowercase();
+        PATTERNS
+            .iter()
+            .find(|(_, needles)| needles.iter().any(|n| text.contains(n)))
+            .map_or(Self::Other, |(kind, _)| *kind)
+    }
+
+    /// What the user can do about it.
+    pub fn hint(&self) -> &'static str {
+        match self {
+            Self::Unavailable => "The video was removed or never existed; check the URL",
+            Self::Private => {
+                "Private videos need an account with access: pass --cookies or --cookies-from-browser"
+            }
+            Self::GeoBlocked => "Try a --proxy in a country where the video is available",
+            Self::AgeRestricted => "Sign in with --cookies-from-browser <browser> or --cookies <file>",
+            Self::MembersOnly => {
+                "Pass cookies of an account with a channel membership (--cookies-from-browser)"
+            }
+            Self::RateLimited => {
+                "Slow down with --sleep-interval/--sleep-requests or a lower --concurrency"
+            }
+            Self::Network => "Check your connection and any --proxy settings",
+            Self::FfmpegMissing => "Install ffmpeg and make sure it is on your PATH",
+            Self::DiskFull => "Free up space or pick another --output directory",
+            Self::UnsupportedUrl => "yt-dlp doesn't recognize this URL; check it for typos",
+            Self::Other => "Updating yt-dlp (yt-dlp -U) fixes many extraction errors",
+        }
+    }
+
+    /// Process exit code when a run stops on this kind of failure.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Unavailable => 10,
+            Self::Private => 11,
+            Self::GeoBlocked => 12,
+            Self::AgeRestricted => 13,
+            Self::MembersOnly => 14,
+            Self::RateLimited => 15,
+            Self::Network => 16,
+            Self::FfmpegMissing => 17,
+            Self::DiskFull => 18,
+            Self::UnsupportedUrl => 19,
+            Self::Other => crate::error::EXIT_ERROR,
+        }
+    }
+}
+
+impl fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Self::Unavailable => "unavailable",
+            Self::Private => "private",
+            Self::GeoBlocked => "geo-blocked",
+            Self::AgeRestricted => "age-restricted",
+            Self::MembersOnly => "members-only",
+            Self::RateLimited => "rate-limited",
+            Self::Network => "network",
+            Self::FfmpegMissing => "ffmpeg missing",
+            Self::DiskFull => "disk full",
+            Self::UnsupportedUrl => "unsupported URL",
+            Self::Other => "other",
+        };
+        write!(f, "{label}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let cases = [
+            ("ERROR: [youtube] abc: Video unavailable", FailureKind::Unavailable),
+            ("ERROR: [youtube] abc: Private video. Sign in if you've been granted access", FailureKind::Private),
+            (
+                "ERROR: [youtube] abc: The uploader has not made this video available in your country",
+                FailureKind::GeoBlocked,
+            ),
+            ("ERROR: [youtube] abc: Sign in to confirm your age", FailureKind::AgeRestricted),
+            (
+                "ERROR: [youtube] abc: Join this channel to get access to members-only content",
+                FailureKind::MembersOnly,
+            ),
+            ("ERROR: unable to download video data: HTTP Error 429: Too Many Requests", FailureKind::RateLimited),
+            ("ERROR: Postprocessing: ffprobe and ffmpeg not found", FailureKind::FfmpegMissing),
+            ("ERROR: unable to write data: [Errno 28] No space left on device", FailureKind::DiskFull),
+            ("ERROR: Unsupported URL: https://example.com", FailureKind::UnsupportedUrl),
+            ("ERROR: Unable to download webpage: <urlopen error timed out>", FailureKind::Network),
+            ("ERROR: something new", FailureKind::Other),
+        ];
+        for (stderr, kind) in cases {
+            assert_eq!(FailureKind::classify(stderr), kind, "{stderr}");
+        }
+    }
+}
@@ -0,0 +1,454 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+
+use super::export::{uri_encode, xml_escape};
+use super::http::{self, Request, Response};
+
+use crate::error::{AppError, Result};
+use crate::model::format::MediaFormat;
+use crate::model::video::{date_from_timestamp, timestamp_from_date, Chapter, YtDlpInfoJson};
+
+/// Where `feed --serve` listens by default.
+pub const DEFAULT_ADDR: &str = "127.0.0.1:8080";
+
+/// Name of the feed file, in the directory and on the server.
+pub const FEED_FILE: &str = "feed.xml";
+
+const INFO_SUFFIX: &str = ".info.json";
+
+/// One media file of the directory, with what its `.info.json` says about it.
+#[derive(Debug, Clone)]
+pub struct Episode {
+    /// Relative to the feed directory, `/`-separated
+    pub path: String,
+    pub size: u64,
+    pub mime_type: &'static str,
+    pub title: String,
+    pub description: Option<String>,
+    pub duration: Option<f64>,
+    /// Unix time of the upload, or of the file's last change when unknown
+    pub published: i64,
+    pub guid: String,
+    pub link: Option<String>,
+    pub image: Option<String>,
+    pub author: Option<String>,
+    pub chapters: Vec<Chapter>,
+}
+
+/// A podcast feed of the media files in a directory. The directory is read
+/// again on every `render`, so the feed follows new downloads.
+#[derive(Debug, Clone)]
+pub struct Feed {
+    pub dir: PathBuf,
+    /// Where `dir` is served from, without a trailing `/`
+    pub base_url: String,
+    /// The directory name if `None`
+    pub title: Option<String>,
+}
+
+impl Feed {
+    pub fn new(dir: impl Into<PathBuf>, base_url: &str, title: Option<String>) -> Result<Self> {
+        let base_url = base_url.trim().trim_end_matches('/');
+        if !(base_url.starts_with("http://") || base_url.starts_with("https://")) {
+            return Err(AppError::InvalidBaseUrl(base_url.to_string()));
+        }
+        Ok(Self {
+            dir: dir.into(),
+            base_url: base_url.to_string(),
+            title,
+        })
+    }
+
+    /// The media files of the directory and its subdirectories, newest
+    /// first.
+    pub fn episodes(&self) -> Result<Vec<Episode>> {
+        let mut files = Vec::new();
+        walk(&self.dir, &mut files)?;
+
+        let mut infos = HashMap::new();
+        for path in &files {
+            let name = path.to_string_lossy();
+            let Some(key) = name.strip_suffix(INFO_SUFFIX) else {
+                continue;
+            };
+            match read_info(path) {
+                Ok(info) => {
+                    infos.insert(PathBuf::from(key), info);
+                }
+                Err(e) => log::warn!("Ignoring {}: {e}", path.display()),
+            }
+        }
+
+        let mut episodes = Vec::new();
+        for path in &files {
+            let Some(format) = media_format(path) else {
+                continue;
+            };
+            let metadata = std::fs::metadata(path)?;
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_secs() as i64);
+            let relative = path.strip_prefix(&self.dir).unwrap_or(path);
+            let relative: Vec<_> = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect();
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+
+            let mut episode = Episode {
+                path: relative.join("/"),
+                size: metadata.len(),
+                mime_type: format.mime_type(),
+                title: stem.clone(),
+                description: None,
+                duration: None,
+                published: modified,
+                guid: relative.join("/"),
+                link: None,
+                image: None,
+                author: None,
+                chapters: Vec::new(),
+            };
+            if let Some((info, whole)) = find_info(&infos, path) {
+                describe(&mut episode, info, whole);
+            }
+            episodes.push(episode);
+        }
+
+        episodes.sort_by(|a, b| b.published.cmp(&a.published).then(a.path.cmp(&b.path)));
+        Ok(episodes)
+    }
+
+    /// The title, or the name of the directory.
+    fn channel_title(&self) -> Result<String> {
+        Ok(match &self.title {
+            Some(title) => title.clone(),
+            None => std::fs::canonicalize(&self.dir)?
+                .file_name()
+                .map_or("yt-grab".to_string(), |n| n.to_string_lossy().to_string()),
+        })
+    }
+
+    /// The RSS 2.0 feed, with iTunes and Podlove Simple Chapters tags.
+    pub fn render(&self) -> Result<String> {
+        Ok(render_feed(&self.channel_title()?, &self.base_url, &self.episodes()?))
+    }
+
+    /// Write the feed to `path`. Returns the number of episodes.
+    pub fn write(&self, path: &Path) -> Result<usize> {
+        let episodes = self.episodes()?;
+        std::fs::write(path, render_feed(&self.channel_title()?, &self.base_url, &episodes))?;
+        Ok(episodes.len())
+    }
+
+    /// Serve the feed (at `/` and `/feed.xml`) and the media files until
+    /// `shutdown` fires.
+    pub async fn serve(&self, listener: TcpListener, shutdown: CancellationToken) -> Result<()> {
+        loop {
+            let (mut stream, peer) = tokio::select! {
+                accepted = listener.accept() => accepted?,
+                _ = shutdown.cancelled() => return Ok(()),
+            };
+            let feed = self.clone();
+            tokio::spawn(async move {
+                let (reader, mut writer) = stream.split();
+                let request = match http::read_request(reader).await {
+                    Ok(Some(request)) => request,
+                    Ok(None) => return,
+                    Err(e) => {
+                        let response = Response::read_error(&e);
+                        let _ = http::write_response(writer, &response, false).await;
+                        return;
+                    }
+                };
+                log::debug!("{peer}: {} {}", request.method, request.path);
+                let head_only = request.method == "HEAD";
+                let result = match feed.handle(&request) {
+                    Route::Response(response) => {
+                        http::write_response(&mut writer, &response, head_only).await
+                    }
+                    Route::Feed => {
+                        // Rendering walks the directory and reads info files
+                        let rendered = tokio::task::spawn_blocking(move || feed.render())
+                            .await
+                            .unwrap_or_else(|e| Err(std::io::Error::other(e).into()));
+                        let response = match rendered {
+                            Ok(xml) => Response {
+                                status: 200,
+                                content_type: "application/rss+xml; charset=utf-8",
+                                body: xml.into_bytes(),
+                            },
+                            Err(e) => Response::error(500, e.to_string()),
+                        };
+                        http::write_response(&mut writer, &response, head_only).await
+                    }
+                    Route::File(path, mime_type) => {
+                        let range = request.header("range");
+                        http::write_file(&mut writer, &path, mime_type, range, head_only).await
+                    }
+                };
+                if let Err(e) = result {
+                    log::debug!("{peer}: {e}");
+                }
+            });
+        }
+    }
+
+    fn handle(&self, request: &Request) -> Route {
+        if request.method != "GET" && request.method != "HEAD" {
+            return Route::Response(Response::error(405, "only GET and HEAD are supported"));
+        }
+        if request.path == "/" || request.path == format!("/{FEED_FILE}") {
+            return Route::Feed;
+        }
+
+        // Only media files, and nothing outside the directory or hidden
+        let not_found = Route::Response(Response::error(404, "no such file"));
+        let Some(path) = http::percent_decode(request.path.trim_start_matches('/')) else {
+            return not_found;
+        };
+        let path = Path::new(&path);
+        let safe = path.components().all(|c| match c {
+            Component::Normal(name) => !name.to_string_lossy().starts_with('.'),
+            _ => false,
+        });
+        let path = self.dir.join(path);
+        match media_format(&path) {
+            Some(format) if safe && path.is_file() => Route::File(path, format.mime_type()),
+            _ => not_found,
+        }
+    }
+}
+
+enum Route {
+    Response(Response),
+    /// The feed, rendered afresh
+    Feed,
+    File(PathBuf, &'static str),
+}
+
+/// Collect the files under `dir`, skipping hidden ones.
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            walk(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// The format of a finished media file; `None` for other files, including
+/// post-processing temporaries.
+fn media_format(path: &Path) -> Option<MediaFormat> {
+    let stem = path.file_stem()?.to_string_lossy();
+    if stem.ends_with(".temp") {
+        return None;
+    }
+    MediaFormat::from_extension(&path.extension()?.to_string_lossy())
+}
+
+fn read_info(path: &Path) -> Result<YtDlpInfoJson> {
+    let json = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&json)?)
+}
+
+/// The info of the video `path` was downloaded from, and whether the file
+/// is the whole video rather than a section (`<title> - <section>.<ext>`).
+fn find_info<'a>(
+    infos: &'a HashMap<PathBuf, YtDlpInfoJson>,
+    path: &Path,
+) -> Option<(&'a YtDlpInfoJson, bool)> {
+    let stem = path.file_stem()?.to_string_lossy();
+    if let Some(info) = infos.get(&path.with_file_name(&*stem)) {
+        return Some((info, true));
+    }
+    infos
+        .iter()
+        .filter(|(key, _)| key.parent() == path.parent())
+        .filter_map(|(key, info)| {
+            let title = key.file_name()?.to_string_lossy();
+            stem.starts_with(&format!("{title} - ")).then_some((title.len(), info))
+        })
+        .max_by_key(|(len, _)| *len)
+        .map(|(_, info)| (info, false))
+}
+
+/// Fill in `episode` from its video's info. Sections keep their file name as
+/// title, and don't get the video's duration or chapters.
+fn describe(episode: &mut Episode, info: &YtDlpInfoJson, whole: bool) {
+    if let Some(published) = info
+        .timestamp
+        .map(|t| t as i64)
+        .or_else(|| info.upload_date.as_deref().and_then(timestamp_from_date))
+    {
+        episode.published = published;
+    }
+    if let Some(id) = &info.id {
+        episode.guid = if whole {
+            id.clone()
+        } else {
+            format!("{id}/{}", episode.title)
+        };
+    }
+    if whole {
+        if let Some(title) = &info.title {
+            episode.title = title.clone();
+        }
+        episode.duration = info.duration;
+        episode.chapters = info.chapters.clone().unwrap_or_default();
+    }
+    episode.description = info.description.clone().filter(|d| !d.trim().is_empty());
+    episode.link = info.webpage_url.clone();
+    episode.image = info.thumbnail.clone();
+    episode.author = info.channel.clone().or_else(|| info.uploader.clone());
+}
+
+/// `Tue, 05 Mar 2024 14:30:00 +0000`
+fn rfc2822(ts: i64) -> String {
+    const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let date = date_from_timestamp(ts);
+    let month: usize = date[4..6].parse().unwrap_or(1);
+    let secs = ts.rem_euclid(86_400);
+    format!(
+        "{}, {} {} {} {:02}:{:02}:{:02} +0000",
+        DAYS[ts.div_euclid(86_400).rem_euclid(7) as usize],
+        &date[6..],
+        MONTHS[month - 1],
+        &date[..4],
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// `HH:MM:SS`
+fn clock(secs: f64) -> String {
+    let secs = secs.max(0.0) as u64;
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+fn render_feed(title: &str, base_url: &str, episodes: &[Episode]) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss version=\"2.0\" xmlns:itunes=\"http://www.itunes.com/dtds/podcast-1.0.dtd\" xmlns:psc=\"http://podlove.org/simple-chapters\">\n  <channel>\n",
+    );
+    let title = xml_escape(title);
+    out.push_str(&format!("    <title>{title}</title>\n"));
+    out.push_str(&format!("    <link>{}/</link>\n", xml_escape(base_url)));
+    out.push_str(&format!("    <description>{title}</description>\n"));
+    out.push_str("    <generator>yt-grab</generator>\n");
+    if let Some(author) = episodes.iter().find_map(|e| e.author.as_deref()) {
+        out.push_str(&format!("    <itunes:author>{}</itunes:author>\n", xml_escape(author)));
+    }
+    if let Some(image) = episodes.iter().find_map(|e| e.image.as_deref()) {
+        out.push_str(&format!("    <itunes:image href=\"{}\"/>\n", xml_escape(image)));
+    }
+    out.push_str("    <itunes:explicit>false</itunes:explicit>\n");
+
+    for episode in episodes {
+        out.push_str("    <item>\n");
+        out.push_str(&format!("      <title>{}</title>\n", xml_escape(&episode.title)));
+        if let Some(description) = &episode.description {
+            out.push_str(&format!(
+                "      <description>{}</description>\n",
+                xml_escape(description)
+            ));
+        }
+        out.push_str(&format!(
+            "      <enclosure url=\"{}/{}\" length=\"{}\" type=\"{}\"/>\n",
+            xml_escape(base_url),
+            xml_escape(&uri_encode(&episode.path)),
+            episode.size,
+            episode.mime_type
+        ));
+        out.push_str(&format!(
+            "      <guid isPermaLink=\"false\">{}</guid>\n",
+            xml_escape(&episode.guid)
+        ));
+        out.push_str(&format!("      <pubDate>{}</pubDate>\n", rfc2822(episode.published)));
+        if let Some(link) = &episode.link {
+            out.push_str(&format!("      <link>{}</link>\n", xml_escape(link)));
+        }
+        if let Some(duration) = episode.duration {
+            out.push_str(&format!(
+                "      <itunes:duration>{}</itunes:duration>\n",
+                clock(duration)
+            ));
+        }
+        if let Some(image) = &episode.image {
+            out.push_str(&format!("      <itunes:image href=\"{}\"/>\n", xml_escape(image)));
+        }
+        if !episode.chapters.is_empty() {
+            out.push_str("      <psc:chapters version=\"1.2\">\n");
+            for chapter in &episode.chapters {
+                out.push_str(&format!(
+                    "        <psc:chapter start=\"{}\" title=\"{}\"/>\n",
+                    clock(chapter.start_time),
+                    xml_escape(&chapter.title)
+                ));
+            }
+            out.push_str("      </psc:chapters>\n");
+        }
+        out.push_str("    </item>\n");
+    }
+    out.push_str("  </channel>\n</rss>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc2822() {
+        assert_eq!(rfc2822(0), "Thu, 01 Jan 1970 00:00:00 +0000");
+        let ts = timestamp_from_date("20240305").unwrap() + 14 * 3600 + 30 * 60;
+        assert_eq!(rfc2822(ts), "Tue, 05 Mar 2024 14:30:00 +0000");
+        assert!(timestamp_from_date("2024-03-05").is_none());
+    }
+
+    #[test]
+    fn test_render_feed() {
+        let episode = Episode {
+            path: "talks/Rock & Roll.mp3".into(),
+            size: 1234,
+            mime_type: "audio/mpeg",
+            title: "Rock & Roll".into(),
+            description: Some("Live <3".into()),
+            duration: Some(3725.0),
+            published: 0,
+            guid: "abc".into(),
+            link: None,
+            image: None,
+            author: None,
+            chapters: vec![Chapter {
+                title: "Intro".into(),
+                start_time: 90.0,
+                end_time: 120.0,
+            }],
+        };
+        let xml = render_feed("Mix", "https://example.com/pod", &[episode]);
+        assert!(xml.contains(
+            "<enclosure url=\"https://example.com/pod/talks/Rock%20%26%20Roll.mp3\" length=\"1234\" type=\"audio/mpeg\"/>"
+        ));
+        assert!(xml.contains("<title>Rock &amp; Roll</title>"));
+        assert!(xml.contains("<description>Live &lt;3</description>"));
+        assert!(xml.contains("<itunes:duration>01:02:05</itunes:duration>"));
+        assert!(xml.contains("<psc:chapter start=\"00:01:30\" title=\"Intro\"/>"));
+    }
+}
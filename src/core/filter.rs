@@ -0,0 +1,162 @@
+use regex::Regex;
+
+use crate::error::{AppError, Result};
+use crate::model::video::VideoInfo;
+
+/// Narrows a fetched playlist before selection.
+///
+/// Entries whose duration or upload date is unknown are kept, since flat
+/// playlist listings don't always carry them.
+#[derive(Debug, Clone, Default)]
+pub struct EntryFilter {
+    pub min_duration: Option<f64>,
+    pub max_duration: Option<f64>,
+    pub match_title: Option<Regex>,
+    pub reject_title: Option<Regex>,
+    /// Inclusive lower bound, `YYYYMMDD`
+    pub date_after: Option<String>,
+    /// Inclusive upper bound, `YYYYMMDD`
+    pub date_before: Option<String>,
+}
+
+impl EntryFilter {
+    pub fn matches(&self, video: &VideoInfo) -> bool {
+        if let Some(duration) = video.duration {
+            if self.min_duration.is_some_and(|min| duration < min)
+                || self.max_duration.is_some_and(|max| duration > max)
+            {
+                return false;
+            }
+        }
+
+        if let Some(re) = &self.match_title {
+            if !re.is_match(&video.title) {
+                return false;
+            }
+        }
+        if let Some(re) = &self.reject_title {
+            if re.is_match(&video.title) {
+                return false;
+            }
+        }
+
+        if let Some(date) = &video.upload_date {
+            if self.date_after.as_ref().is_some_and(|after| date < after)
+                || self
+                    .date_before
+                    .as_ref()
+                    .is_some_and(|before| date > before)
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Keep matching entries, renumbered from 1 so selection ranges apply to
+    /// the filtered list. Returns the kept entries and the filtered-out count.
+    pub fn apply(&self, videos: Vec<VideoInfo>) -> (Vec<VideoInfo>, usize) {
+        let total = videos.len();
+        let kept: Vec<VideoInfo> = videos
+            .into_iter()
+            .filter(|v| self.matches(v))
+            .enumerate()
+            .map(|(i, mut v)| {
+                v.index = i + 1;
+                v
+            })
+            .collect();
+        let removed = total - kept.len();
+        (kept, removed)
+    }
+}
+
+/// Parse a duration given as seconds ("90") or clock time ("1:30", "1:02:03").
+pub fn parse_duration(input: &str) -> Result<f64> {
+    let invalid = || AppError::InvalidFilter(format!("invalid duration \"{input}\""));
+
+    let mut total = 0.0;
+    let parts: Vec<&str> = input.trim().split(':').collect();
+    if parts.len() > 3 {
+        return Err(invalid());
+    }
+    for part in parts {
+        let value: f64 = part.trim().parse().map_err(|_| invalid())?;
+        if value < 0.0 {
+            return Err(invalid());
+        }
+        total = total * 60.0 + value;
+    }
+    Ok(total)
+}
+
+/// Normalize a date given as `YYYYMMDD` or `YYYY-MM-DD` to `YYYYMMDD`.
+pub fn parse_date(input: &str) -> Result<String> {
+    let digits: String = input.trim().chars().filter(|c| *c != '-').collect();
+    if digits.len() != 8 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(AppError::InvalidFilter(format!(
+            "invalid date \"{input}\" (expected YYYYMMDD or YYYY-MM-DD)"
+        )));
+    }
+    Ok(digits)
+}
+
+pub fn parse_regex(input: &str) -> Result<Regex> {
+    Regex::new(input).map_err(|e| AppError::InvalidFilter(format!("invalid regex: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn video(title: &str, duration: Option<f64>, date: Option<&str>) -> VideoInfo {
+        VideoInfo {
+            title: title.into(),
+            duration,
+            upload_date: date.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    fn filter() -> EntryFilter {
+        EntryFilter {
+            min_duration: Some(60.0),
+            max_duration: None,
+            match_title: None,
+            reject_title: Some(parse_regex("(?i)trailer").unwrap()),
+            date_after: Some(parse_date("2024-01-01").unwrap()),
+            date_before: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90").unwrap(), 90.0);
+        assert_eq!(parse_duration("1:30").unwrap(), 90.0);
+        assert_eq!(parse_duration("1:00:05").unwrap(), 3605.0);
+        assert!(parse_duration("1:x").is_err());
+    }
+
+    #[test]
+    fn test_matches() {
+        let f = filter();
+        assert!(f.matches(&video("Episode 1", Some(600.0), Some("20240105"))));
+        assert!(!f.matches(&video("Episode 2", Some(30.0), Some("20240105"))));
+        assert!(!f.matches(&video("Official Trailer", Some(600.0), None)));
+        assert!(!f.matches(&video("Episode 3", Some(600.0), Some("20231231"))));
+        // Unknown metadata is kept
+        assert!(f.matches(&video("Episode 4", None, None)));
+    }
+
+    #[test]
+    fn test_apply_renumbers() {
+        let (kept, removed) = filter().apply(vec![
+            video("a", Some(10.0), None),
+            video("b", Some(100.0), None),
+            video("c", Some(100.0), None),
+        ]);
+        assert_eq!(removed, 1);
+        assert_eq!(kept.iter().map(|v| v.index).collect::<Vec<_>>(), vec![1, 2]);
+    }
+}
@@ -0,0 +1,398 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio_util::sync::CancellationToken;
+
+use super::downloader::{download_queue, DownloadResult};
+use super::filter::EntryFilter;
+use super::playlist::{Listing, YtDlpFetcher};
+use super::progress::{CallbackReporter, EventBus, ProgressEvent, ProgressReporter};
+use super::queue::DownloadQueue;
+use super::range_parser::parse_ranges;
+
+use crate::error::{AppError, Result};
+use crate::model::audio::AudioProcessing;
+use crate::model::config::{DownloadConfig, QueueOrder};
+use crate::model::format::{AudioCodec, MediaFormat};
+use crate::model::hook::HookConfig;
+use crate::model::network::NetworkConfig;
+use crate::model::video::VideoInfo;
+
+/// A fetched playlist (or single video) after filtering.
+#[derive(Debug, Clone)]
+pub struct Playlist {
+    pub title: Option<String>,
+    /// Numbered from 1, in playlist order
+    pub videos: Vec<VideoInfo>,
+    /// Entries removed by the filter
+    pub filtered_out: usize,
+}
+
+impl Playlist {
+    /// A single video URL rather than a playlist.
+    pub fn is_single(&self) -> bool {
+        self.videos.len() == 1 && self.title.is_none()
+    }
+
+    /// Keep only the items picked by a selection like `"1,3-5"` (see
+    /// `parse_ranges` for the syntax). The kept items are numbered from 1
+    /// again, so a later selection refers to the new list.
+    pub fn select(&mut self, items: &str) -> Result<()> {
+        let indices = parse_ranges(items, self.videos.len())?;
+        self.videos.retain(|v| indices.contains(&v.index));
+        for (i, video) in self.videos.iter_mut().enumerate() {
+            video.index = i + 1;
+        }
+        Ok(())
+    }
+}
+
+/// The fetch → filter → select → download pipeline, without any terminal
+/// output: progress is published on an `EventBus`.
+///
+/// ```no_run
+/// # async fn example() -> yt_grab::Result<()> {
+/// use yt_grab::{Grabber, ProgressEvent};
+/// use tokio_util::sync::CancellationToken;
+///
+/// let grabber = Grabber::builder()
+///     .output_dir("music")
+///     .on_event(|event| {
+///         if let ProgressEvent::Finished { item } = event {
+///             println!("item {item} done");
+///         }
+///     })
+///     .build();
+/// let results = grabber
+///     .run("https://www.youtube.com/playlist?list=...", Some("1-3"), CancellationToken::new())
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Grabber {
+    fetcher: YtDlpFetcher,
+    filter: EntryFilter,
+    tabs: Option<String>,
+    config: DownloadConfig,
+    events: EventBus,
+}
+
+impl Grabber {
+    pub fn builder() -> GrabberBuilder {
+        GrabberBuilder::default()
+    }
+
+    /// A builder with this grabber's settings, but none of its event
+    /// subscribers.
+    pub fn to_builder(&self) -> GrabberBuilder {
+        GrabberBuilder {
+            filter: self.filter.clone(),
+            tabs: self.tabs.clone(),
+            config: self.config.clone(),
+            events: EventBus::default(),
+        }
+    }
+
+    pub fn config(&self) -> &DownloadConfig {
+        &self.config
+    }
+
+    /// The fetcher, for per-video lookups such as `fetch_details`.
+    pub fn fetcher(&self) -> &YtDlpFetcher {
+        &self.fetcher
+    }
+
+    /// Subscribe here (or `attach` a reporter) before `download` to follow
+    /// its progress.
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
+    /// Change the format after building, e.g. once the user picked one.
+    pub fn set_format(&mut self, format: MediaFormat) {
+        self.config.format = format;
+    }
+
+    /// List a playlist or video and apply the entry filter. The tabs or
+    /// playlists of a channel are listed as chosen with `tabs`, by default
+    /// the Videos tab (see `Listing::default_tabs`).
+    pub fn fetch(&self, url: &str) -> Result<Playlist> {
+        let listing = self.list(url)?;
+        let chosen = match &self.tabs {
+            Some(tabs) if listing.has_playlists() => Some(listing.choose(tabs)?),
+            _ => listing.default_tabs(),
+        };
+        self.expand(listing, chosen.as_deref())
+    }
+
+    /// List a URL without expanding the tabs or playlists of a channel, to
+    /// pick some for `expand`.
+    pub fn list(&self, url: &str) -> Result<Listing> {
+        self.fetcher.list(url)
+    }
+
+    /// Flatten `listing` with the nested playlists numbered in `chosen` (all
+    /// if `None`) and apply the entry filter.
+    pub fn expand(&self, listing: Listing, chosen: Option<&[usize]>) -> Result<Playlist> {
+        let (title, videos) = self.fetcher.flatten(listing, chosen)?;
+        let (videos, filtered_out) = self.filter.apply(videos);
+        if videos.is_empty() {
+            return Err(AppError::AllFiltered(filtered_out));
+        }
+        Ok(Playlist {
+            title,
+            videos,
+            filtered_out,
+        })
+    }
+
+    /// An empty queue in the configured order, reporting on `events`.
+    pub fn queue(&self) -> DownloadQueue {
+        DownloadQueue::new(self.config.order, self.events.clone())
+    }
+
+    /// Download every video of `playlist`.
+    pub async fn download(
+        &self,
+        playlist: &Playlist,
+        cancel: CancellationToken,
+    ) -> Vec<DownloadResult> {
+        let queue = self.queue();
+        for video in &playlist.videos {
+            queue.push(video.clone(), 0);
+        }
+        queue.close();
+        self.download_queue(&queue, playlist.title.clone(), cancel).await
+    }
+
+    /// Download from `queue` until it is closed and drained. Items can be
+    /// added, paused and reprioritized meanwhile through a clone of it.
+    pub async fn download_queue(
+        &self,
+        queue: &DownloadQueue,
+        playlist_title: Option<String>,
+        cancel: CancellationToken,
+    ) -> Vec<DownloadResult> {
+        let config = DownloadConfig {
+            playlist_title,
+            ..self.config.clone()
+        };
+        download_queue(queue, &config, &self.events, cancel).await
+    }
+
+    /// Fetch, select `items` (all if `None`) and download.
+    pub async fn run(
+        &self,
+        url: &str,
+        items: Option<&str>,
+        cancel: CancellationToken,
+    ) -> Result<Vec<DownloadResult>> {
+        let mut playlist = self.fetch(url)?;
+        if let Some(items) = items {
+            playlist.select(items)?;
+        }
+        Ok(self.download(&playlist, cancel).await)
+    }
+}
+
+/// Builds a `Grabber`. Defaults match the CLI's: MP3 into the current
+/// directory, three downloads at a time, no limits and no filter.
+pub struct GrabberBuilder {
+    filter: EntryFilter,
+    tabs: Option<String>,
+    config: DownloadConfig,
+    events: EventBus,
+}
+
+impl Default for GrabberBuilder {
+    fn default() -> Self {
+        Self {
+            filter: EntryFilter::default(),
+            tabs: None,
+            config: DownloadConfig {
+                format: MediaFormat::Audio(AudioCodec::Mp3),
+                output_dir: PathBuf::from("."),
+                concurrency: 3,
+                order: QueueOrder::Playlist,
+                rate_limit: None,
+                sleep_interval: None,
+                sleep_requests: None,
+                min_free_space: None,
+                network: NetworkConfig::default(),
+                audio: AudioProcessing::default(),
+                hooks: HookConfig::default(),
+                fail_fast: false,
+                write_info_json: false,
+                playlist_title: None,
+            },
+            events: EventBus::default(),
+        }
+    }
+}
+
+impl GrabberBuilder {
+    pub fn format(mut self, format: MediaFormat) -> Self {
+        self.config.format = format;
+        self
+    }
+
+    pub fn output_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.config.output_dir = dir.into();
+        self
+    }
+
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.config.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn order(mut self, order: QueueOrder) -> Self {
+        self.config.order = order;
+        self
+    }
+
+    /// Total bandwidth in bytes/s, shared by concurrent downloads.
+    pub fn rate_limit(mut self, rate: Option<u64>) -> Self {
+        self.config.rate_limit = rate;
+        self
+    }
+
+    pub fn sleep_interval(mut self, delay: Option<Duration>) -> Self {
+        self.config.sleep_interval = delay;
+        self
+    }
+
+    pub fn sleep_requests(mut self, delay: Option<Duration>) -> Self {
+        self.config.sleep_requests = delay;
+        self
+    }
+
+    /// Hold new downloads while the output filesystem has less free space
+    /// than this, in bytes.
+    pub fn min_free_space(mut self, bytes: Option<u64>) -> Self {
+        self.config.min_free_space = bytes;
+        self
+    }
+
+    pub fn network(mut self, network: NetworkConfig) -> Self {
+        self.config.network = network;
+        self
+    }
+
+    pub fn audio(mut self, audio: AudioProcessing) -> Self {
+        self.config.audio = audio;
+        self
+    }
+
+    pub fn hooks(mut self, hooks: HookConfig) -> Self {
+        self.config.hooks = hooks;
+        self
+    }
+
+    pub fn fail_fast(mut self, fail_fast: bool) -> Self {
+        self.config.fail_fast = fail_fast;
+        self
+    }
+
+    /// Save each item's metadata as `<title>.info.json`, e.g. for a podcast
+    /// feed.
+    pub fn write_info_json(mut self, write: bool) -> Self {
+        self.config.write_info_json = write;
+        self
+    }
+
+    pub fn filter(mut self, filter: EntryFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Which tabs or playlists of a channel URL `fetch` lists, like
+    /// `"videos,shorts"` or `"1,3"` (see `Listing::choose`). Without them
+    /// `fetch` lists the Videos tab.
+    pub fn tabs(mut self, tabs: Option<String>) -> Self {
+        self.tabs = tabs;
+        self
+    }
+
+    pub fn reporter(self, reporter: Arc<dyn ProgressReporter>) -> Self {
+        self.events.attach(reporter);
+        self
+    }
+
+    /// Report progress to a closure. Called from the download tasks, so it
+    /// must be cheap and thread-safe.
+    pub fn on_event(self, f: impl Fn(ProgressEvent) + Send + Sync + 'static) -> Self {
+        self.reporter(Arc::new(CallbackReporter(f)))
+    }
+
+    pub fn build(self) -> Grabber {
+        Grabber {
+            fetcher: YtDlpFetcher {
+                network: self.config.network.clone(),
+                sleep_requests: self.config.sleep_requests,
+            },
+            filter: self.filter,
+            tabs: self.tabs,
+            config: self.config,
+            events: self.events,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playlist(title: Option<&str>, count: usize) -> Playlist {
+        Playlist {
+            title: title.map(String::from),
+            videos: (1..=count)
+                .map(|index| VideoInfo {
+                    index,
+                    id: format!("id{index}"),
+                    title: format!("Video {index}"),
+                    ..Default::default()
+                })
+                .collect(),
+            filtered_out: 0,
+        }
+    }
+
+    #[test]
+    fn test_builder_defaults() {
+        let grabber = Grabber::builder().build();
+        let config = grabber.config();
+        assert_eq!(config.format, MediaFormat::Audio(AudioCodec::Mp3));
+        assert_eq!(config.output_dir, PathBuf::from("."));
+        assert_eq!(config.concurrency, 3);
+        assert_eq!(config.order, QueueOrder::Playlist);
+        assert!(config.rate_limit.is_none() && config.min_free_space.is_none());
+        assert!(config.audio.is_empty());
+        assert!(!config.fail_fast && !config.write_info_json);
+        assert!(grabber.tabs.is_none());
+        // Zero concurrency would never start anything
+        assert_eq!(Grabber::builder().concurrency(0).build().config().concurrency, 1);
+    }
+
+    #[test]
+    fn test_select_renumbers() {
+        let mut list = playlist(Some("Mix"), 5);
+        list.select("2,4-5").unwrap();
+        let picked: Vec<_> = list.videos.iter().map(|v| (v.index, v.id.as_str())).collect();
+        assert_eq!(picked, vec![(1, "id2"), (2, "id4"), (3, "id5")]);
+
+        // A second selection refers to the new numbering
+        list.select("3").unwrap();
+        assert_eq!(list.videos[0].id, "id5");
+        assert!(list.select("4").is_err());
+    }
+
+    #[test]
+    fn test_is_single() {
+        assert!(playlist(None, 1).is_single());
+        assert!(!playlist(Some("Mix"), 1).is_single());
+        assert!(!playlist(None, 2).is_single());
+    }
+}
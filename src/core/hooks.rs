@@ -0,0 +1,136 @@
+use std::process::Stdio;
+
+use tokio::process::Command;
+use tokio_util::sync::CancellationToken;
+
+use super::downloader::DownloadResult;
+use super::process::{self, command_line};
+
+use crate::error::{AppError, Result};
+use crate::model::config::DownloadConfig;
+use crate::model::hook::Hook;
+use crate::model::output::OutputFile;
+use crate::model::video::VideoInfo;
+
+/// Placeholders available to per-item hooks.
+pub fn item_vars(
+    video: &VideoInfo,
+    file: Option<&OutputFile>,
+    config: &DownloadConfig,
+) -> Vec<(&'static str, String)> {
+    vec![
+        (
+            "path",
+            file.map(|f| f.path.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        ),
+        ("title", video.title.clone()),
+        ("id", video.id.clone()),
+        ("url", video.url.clone()),
+        ("playlist", config.playlist_title.clone().unwrap_or_default()),
+        ("format", config.format.extension().to_string()),
+        ("dir", config.output_dir.to_string_lossy().to_string()),
+    ]
+}
+
+/// Placeholders available to the batch hook. `files` is newline-separated,
+/// so it is mostly useful as the `YTGRAB_FILES` environment variable.
+pub fn batch_vars(results: &[DownloadResult], config: &DownloadConfig) -> Vec<(&'static str, String)> {
+    let succeeded = results.iter().filter(|r| r.success).count();
+    let files: Vec<String> = results
+        .iter()
+        .flat_map(|r| &r.files)
+        .map(|f| f.path.to_string_lossy().to_string())
+        .collect();
+    vec![
+        ("playlist", config.playlist_title.clone().unwrap_or_default()),
+        ("format", config.format.extension().to_string()),
+        ("dir", config.output_dir.to_string_lossy().to_string()),
+        ("succeeded", succeeded.to_string()),
+        ("failed", (results.len() - succeeded).to_string()),
+        ("files", files.join("\n")),
+    ]
+}
+
+/// Run a hook with its placeholders filled in and each variable also exported
+/// as `YTGRAB_<NAME>`. Output is captured; the last stderr line ends up in the
+/// error if the command fails.
+pub async fn run_hook(
+    hook: &Hook,
+    vars: &[(&str, String)],
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let argv = hook.expand(vars);
+    let failed = |reason: String| AppError::Hook {
+        program: hook.program().to_string(),
+        reason,
+    };
+
+    let mut cmd = Command::new(&argv[0]);
+    cmd.args(&argv[1..])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+    for (name, value) in vars {
+        cmd.env(format!("YTGRAB_{}", name.to_uppercase()), value);
+    }
+
+    log::info!("Running hook {}", command_line(&argv[0], &argv[1..]));
+    let child = process::make_cancellable(&mut cmd)
+        .spawn()
+        .map_err(|e| failed(e.to_string()))?;
+    let pid = child.id();
+    let _group = process::track(pid);
+
+    let output = tokio::select! {
+        output = child.wait_with_output() => output.map_err(|e| failed(e.to_string()))?,
+        _ = cancel.cancelled() => {
+            // Dropping the child kills it; give its helpers a chance first
+            process::terminate(pid);
+            return Err(AppError::Cancelled);
+        }
+    };
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    log::info!("Hook {} finished ({})", hook.program(), output.status);
+    if !stderr.trim().is_empty() {
+        log::debug!("Hook stderr:\n{}", stderr.trim_end());
+    }
+    if output.status.success() {
+        return Ok(());
+    }
+    let reason = stderr
+        .lines()
+        .rfind(|l| !l.trim().is_empty())
+        .map(|l| l.trim().to_string())
+        .unwrap_or_else(|| format!("exited with {}", output.status));
+    Err(failed(reason))
+}
+
+/// Run the per-item hook once for each file the item produced (or once with
+/// an empty `{path}` if none were reported). Returns the first failure.
+pub async fn run_item_hooks(
+    hook: &Hook,
+    video: &VideoInfo,
+    files: &[OutputFile],
+    config: &DownloadConfig,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let mut first_error = None;
+    let files: Vec<Option<&OutputFile>> = if files.is_empty() {
+        vec![None]
+    } else {
+        files.iter().map(Some).collect()
+    };
+
+    for file in files {
+        match run_hook(hook, &item_vars(video, file, config), cancel).await {
+            Ok(()) => {}
+            Err(AppError::Cancelled) => return Err(AppError::Cancelled),
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    first_error.map_or(Ok(()), Err)
+}
@@ -0,0 +1,388 @@
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWrite,
+    AsyncWriteExt, BufReader,
+};
+use tokio::net::TcpStream;
+
+/// Larger request bodies are refused; job submissions are tiny.
+const MAX_BODY: usize = 1 << 20;
+
+/// Longest request or header line, and most headers, accepted.
+const MAX_LINE: usize = 8 << 10;
+const MAX_HEADERS: usize = 100;
+
+/// How long a client may take to send its whole request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Just enough HTTP/1.1 for a local JSON API: one request per connection,
+/// bodies sized by `Content-Length`.
+pub struct Request {
+    pub method: String,
+    /// Without the query string
+    pub path: String,
+    pub query: String,
+    /// Names are lowercased
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// The value of `name` in the query string, undecoded.
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        self.query.split('&').find_map(|pair| match pair.split_once('=') {
+            Some((key, value)) if key == name => Some(value),
+            None if pair == name => Some(""),
+            _ => None,
+        })
+    }
+
+    /// The value of header `name` (lowercase).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Whether the `Host` header names this server as reached directly:
+    /// `localhost`, a loopback address or the address it listens on (any IP
+    /// when listening on all interfaces). Requests through another name,
+    /// as in DNS rebinding, are refused.
+    pub fn is_direct(&self, local: SocketAddr) -> bool {
+        let Some(host) = self.header("host") else {
+            return false;
+        };
+        // Strip the port, minding IPv6 brackets
+        let name = match host.rsplit_once(':') {
+            Some((name, port)) if !port.contains(']') => name,
+            _ => host,
+        };
+        let name = name.trim_start_matches('[').trim_end_matches(']');
+        if name.eq_ignore_ascii_case("localhost") {
+            return true;
+        }
+        match name.parse::<IpAddr>() {
+            Ok(ip) => ip.is_loopback() || ip == local.ip() || local.ip().is_unspecified(),
+            Err(_) => false,
+        }
+    }
+
+    /// Whether the body is declared as JSON.
+    pub fn is_json(&self) -> bool {
+        self.header("content-type").is_some_and(|value| {
+            value
+                .split(';')
+                .next()
+                .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("application/json"))
+        })
+    }
+}
+
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn json<T: Serialize>(status: u16, value: &T) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: serde_json::to_vec_pretty(value).unwrap_or_default(),
+        }
+    }
+
+    /// `{"error": message}`
+    pub fn error(status: u16, message: impl Into<String>) -> Self {
+        Self::json(status, &serde_json::json!({ "error": message.into() }))
+    }
+
+    /// The answer to a request `read_request` couldn't read.
+    pub fn read_error(e: &io::Error) -> Self {
+        let status = match e.kind() {
+            io::ErrorKind::TimedOut => 408,
+            _ => 400,
+        };
+        Self::error(status, e.to_string())
+    }
+}
+
+fn status_text(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        206 => "Partial Content",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        416 => "Range Not Satisfiable",
+        _ => "Internal Server Error",
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Read one line of at most `MAX_LINE` bytes.
+async fn read_line<R: AsyncBufRead + Unpin>(reader: &mut R, line: &mut String) -> io::Result<usize> {
+    let read = reader.take(MAX_LINE as u64).read_line(line).await?;
+    if read == MAX_LINE && !line.ends_with('\n') {
+        return Err(invalid("request line or header too long"));
+    }
+    Ok(read)
+}
+
+/// Read one request. `None` if the client disconnected without sending one.
+/// Clients that send too slowly (see `READ_TIMEOUT`) or too much get an
+/// error.
+pub async fn read_request<R: AsyncRead + Unpin>(reader: R) -> io::Result<Option<Request>> {
+    tokio::time::timeout(READ_TIMEOUT, read_request_within(reader))
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "timed out reading the request"))?
+}
+
+async fn read_request_within<R: AsyncRead + Unpin>(reader: R) -> io::Result<Option<Request>> {
+    let mut reader = BufReader::new(reader);
+    let mut line = String::new();
+    if read_line(&mut reader, &mut line).await? == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(invalid("malformed request line"));
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let (method, path, query) = (method.to_string(), path.to_string(), query.to_string());
+
+    let mut length = 0;
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        if read_line(&mut reader, &mut line).await? == 0 {
+            return Err(invalid("connection closed in headers"));
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if headers.len() == MAX_HEADERS {
+            return Err(invalid("too many headers"));
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            let (name, value) = (name.trim().to_lowercase(), value.trim().to_string());
+            if name == "content-length" {
+                length = value.parse().map_err(|_| invalid("bad Content-Length"))?;
+            }
+            headers.push((name, value));
+        }
+    }
+    if length > MAX_BODY {
+        return Err(invalid("request body too large"));
+    }
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+    Ok(Some(Request {
+        method,
+        path,
+        query,
+        headers,
+        body,
+    }))
+}
+
+/// Send `response`; with `head_only` (a `HEAD` request), only its headers.
+pub async fn write_response<W: AsyncWrite + Unpin>(
+    mut writer: W,
+    response: &Response,
+    head_only: bool,
+) -> io::Result<()> {
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        status_text(response.status),
+        response.content_type,
+        response.body.len()
+    );
+    writer.write_all(head.as_bytes()).await?;
+    if !head_only {
+        writer.write_all(&response.body).await?;
+    }
+    writer.flush().await
+}
+
+/// The inclusive byte range a `Range` header asks for in a body of `len`
+/// bytes, or `None` if it can't be satisfied. Only single ranges are
+/// supported.
+fn byte_range(header: &str, len: u64) -> Option<(u64, u64)> {
+    let (start, end) = header.trim().strip_prefix("bytes=")?.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => (len.checked_sub(suffix.parse().ok()?)?, len.checked_sub(1)?),
+        (start, "") => (start.parse().ok()?, len.checked_sub(1)?),
+        (start, end) => (start.parse().ok()?, end.parse::<u64>().ok()?.min(len.checked_sub(1)?)),
+    };
+    (start <= end).then_some((start, end))
+}
+
+/// Send the file at `path` as the response, or the part of it `range` (a
+/// `Range` header) asks for. With `head_only`, send only the headers.
+pub async fn write_file<W: AsyncWrite + Unpin>(
+    mut writer: W,
+    path: &Path,
+    content_type: &str,
+    range: Option<&str>,
+    head_only: bool,
+) -> io::Result<()> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let len = file.metadata().await?.len();
+    let (status, start, end) = match range.map(|r| byte_range(r, len)) {
+        None => (200, 0, len),
+        Some(Some((start, end))) => (206, start, end + 1),
+        Some(None) => {
+            let head = format!(
+                "HTTP/1.1 416 {}\r\nContent-Range: bytes */{len}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                status_text(416)
+            );
+            writer.write_all(head.as_bytes()).await?;
+            return writer.flush().await;
+        }
+    };
+
+    let mut head = format!(
+        "HTTP/1.1 {status} {}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nAccept-Ranges: bytes\r\n",
+        status_text(status),
+        end - start
+    );
+    if status == 206 {
+        head.push_str(&format!("Content-Range: bytes {start}-{}/{len}\r\n", end - 1));
+    }
+    head.push_str("Connection: close\r\n\r\n");
+    writer.write_all(head.as_bytes()).await?;
+    if !head_only {
+        file.seek(io::SeekFrom::Start(start)).await?;
+        tokio::io::copy(&mut file.take(end - start), &mut writer).await?;
+    }
+    writer.flush().await
+}
+
+/// Decode `%XX` escapes in a request path. `None` if they don't decode to
+/// UTF-8.
+pub fn percent_decode(path: &str) -> Option<String> {
+    let bytes = path.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+/// Send a request to `addr` (`host:port`) and return the status code and
+/// body of the response.
+pub async fn request(
+    addr: &str,
+    method: &str,
+    path: &str,
+    body: Option<&str>,
+) -> io::Result<(u16, String)> {
+    let mut stream = TcpStream::connect(addr).await?;
+    let body = body.unwrap_or_default();
+    let head = format!(
+        "{method} {path} HTTP/1.1\r\nHost: {addr}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body.as_bytes()).await?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+    let response = String::from_utf8_lossy(&response);
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| invalid("malformed response"))?;
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| invalid("malformed status line"))?;
+    Ok((status, body.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_request() {
+        let raw = "POST /jobs?wait&id=3 HTTP/1.1\r\nHost: x\r\ncontent-length: 7\r\n\r\n{\"a\":1}";
+        let request = read_request(raw.as_bytes()).await.unwrap().unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/jobs");
+        assert_eq!(request.query_param("id"), Some("3"));
+        assert_eq!(request.query_param("wait"), Some(""));
+        assert_eq!(request.query_param("all"), None);
+        assert_eq!(request.header("host"), Some("x"));
+        assert_eq!(request.body, b"{\"a\":1}");
+    }
+
+    #[tokio::test]
+    async fn test_read_request_limits() {
+        let long = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_LINE));
+        assert!(read_request(long.as_bytes()).await.is_err());
+        let many = format!("GET / HTTP/1.1\r\n{}\r\n", "X-A: 1\r\n".repeat(MAX_HEADERS + 1));
+        assert!(read_request(many.as_bytes()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_is_direct() {
+        let request = |host: &str| {
+            let raw = format!("GET / HTTP/1.1\r\nHost: {host}\r\n\r\n");
+            async move { read_request(raw.as_bytes()).await.unwrap().unwrap() }
+        };
+        let local: SocketAddr = "127.0.0.1:8765".parse().unwrap();
+        assert!(request("127.0.0.1:8765").await.is_direct(local));
+        assert!(request("localhost:8765").await.is_direct(local));
+        assert!(request("[::1]:8765").await.is_direct(local));
+        assert!(!request("evil.example:8765").await.is_direct(local));
+        assert!(!request("192.168.1.5:8765").await.is_direct(local));
+        let all: SocketAddr = "0.0.0.0:8765".parse().unwrap();
+        assert!(request("192.168.1.5:8765").await.is_direct(all));
+        assert!(!request("nas.example").await.is_direct(all));
+    }
+
+    #[test]
+    fn test_byte_range() {
+        assert_eq!(byte_range("bytes=0-99", 1000), Some((0, 99)));
+        assert_eq!(byte_range("bytes=500-", 1000), Some((500, 999)));
+        assert_eq!(byte_range("bytes=-100", 1000), Some((900, 999)));
+        assert_eq!(byte_range("bytes=900-5000", 1000), Some((900, 999)));
+        assert_eq!(byte_range("bytes=1000-", 1000), None);
+        assert_eq!(byte_range("items=0-1", 1000), None);
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("/Rock%20%26%20Roll.mp3").unwrap(), "/Rock & Roll.mp3");
+        assert!(percent_decode("/bad%2").is_none());
+    }
+}
@@ -1,3 +1,19 @@
+pub mod daemon;
+pub mod disk;
 pub mod downloader;
+pub mod export;
+pub mod failure;
+pub mod feed;
+pub mod filter;
+pub mod grabber;
+pub mod hooks;
+pub mod http;
 pub mod playlist;
+pub mod postprocess;
+pub mod process;
+pub mod progress;
+pub mod queue;
 pub mod range_parser;
+pub mod sponsorblock;
+pub mod throttle;
+pub mod watch;
@@ -1,85 +1,433 @@
+use std::collections::HashSet;
 use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::failure::FailureKind;
+use super::process::{self, command_line};
+use super::range_parser::parse_ranges;
 
 use crate::error::{AppError, Result};
-use crate::model::video::{Chapter, VideoInfo, YtDlpPlaylistJson, YtDlpVideoJson};
+use crate::model::network::NetworkConfig;
+use crate::model::video::{
+    Chapter, SizeInfo, VideoInfo, YtDlpEntry, YtDlpPlaylistJson, YtDlpVideoJson,
+};
 
 pub trait PlaylistFetcher {
     fn fetch(&self, url: &str) -> Result<(Option<String>, Vec<VideoInfo>)>;
 }
 
-pub struct YtDlpFetcher;
+/// Metadata that flat playlist listings don't include.
+pub struct VideoDetails {
+    pub chapters: Vec<Chapter>,
+    pub sizes: SizeInfo,
+}
+
+#[derive(Clone, Default)]
+pub struct YtDlpFetcher {
+    pub network: NetworkConfig,
+    /// Pause between the yt-dlp listings of the tabs or playlists `flatten`
+    /// expands
+    pub sleep_requests: Option<Duration>,
+}
 
 impl YtDlpFetcher {
-    /// Fetch full video metadata (including chapters) for a single video URL.
-    pub fn fetch_chapters(url: &str) -> Result<Vec<Chapter>> {
+    /// Run yt-dlp with the network settings and return its stdout.
+    fn run(&self, args: &[&str]) -> Result<Vec<u8>> {
+        let mut full_args = self.network.yt_dlp_args();
+        full_args.extend(args.iter().map(|a| a.to_string()));
+        log::info!("Running {}", self.network.redact(&command_line("yt-dlp", &full_args)));
+
+        let started = Instant::now();
         let output = Command::new("yt-dlp")
-            .args(["-J", "--no-warnings", url])
-            .output()?;
+            .args(&full_args)
+            .output()
+            .map_err(|e| process::spawn_error("yt-dlp", e))?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        log::info!("yt-dlp finished ({}) in {:.1?}", output.status, started.elapsed());
+        if !stderr.trim().is_empty() {
+            log::debug!("yt-dlp stderr:\n{}", self.network.redact(stderr.trim_end()));
+        }
 
         if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(AppError::YtDlp(stderr.trim().to_string()));
+            return Err(AppError::YtDlp {
+                kind: FailureKind::classify(&stderr),
+                message: self.network.redact(stderr.trim()),
+            });
         }
+        Ok(output.stdout)
+    }
 
-        let json: YtDlpVideoJson = serde_json::from_slice(&output.stdout)?;
-        Ok(json.chapters.unwrap_or_default())
+    /// Fetch full video metadata (chapters and format sizes) for a single
+    /// video URL.
+    pub fn fetch_details(&self, url: &str) -> Result<VideoDetails> {
+        let stdout = self.run(&["-J", "--no-warnings", url])?;
+        let json: YtDlpVideoJson = serde_json::from_slice(&stdout)?;
+        Ok(VideoDetails {
+            chapters: json.chapters.unwrap_or_default(),
+            sizes: json.sizes.size_info(),
+        })
     }
 }
 
 impl PlaylistFetcher for YtDlpFetcher {
     fn fetch(&self, url: &str) -> Result<(Option<String>, Vec<VideoInfo>)> {
-        let output = Command::new("yt-dlp")
-            .args(["--flat-playlist", "-J", "--no-warnings", url])
-            .output()?;
+        let listing = self.list(url)?;
+        self.flatten(listing, None)
+    }
+}
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(AppError::YtDlp(stderr.trim().to_string()));
+/// A playlist inside a listing: a tab of a channel ("Videos", "Shorts",
+/// "Live", "Playlists"), or one of the playlists of its Playlists tab.
+#[derive(Debug, Clone)]
+pub struct SubPlaylist {
+    pub title: String,
+    pub url: String,
+    /// Its entries, when yt-dlp listed them already
+    pub entries: Option<Listing>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ListEntry {
+    Video(VideoInfo),
+    Playlist(SubPlaylist),
+}
+
+/// What a URL lists, with nested playlists not expanded yet. Entries are
+/// numbered from 1, videos and playlists alike.
+#[derive(Debug, Clone)]
+pub struct Listing {
+    pub title: Option<String>,
+    pub entries: Vec<ListEntry>,
+}
+
+impl Listing {
+    /// The nested playlists, with their entry numbers.
+    pub fn playlists(&self) -> impl Iterator<Item = (usize, &SubPlaylist)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entry)| match entry {
+                ListEntry::Playlist(sub) => Some((i + 1, sub)),
+                ListEntry::Video(_) => None,
+            })
+    }
+
+    pub fn has_playlists(&self) -> bool {
+        self.playlists().next().is_some()
+    }
+
+    /// The entry numbers listed when no tabs are chosen: the channel's
+    /// Videos tab, or every playlist (`None`) when there isn't one.
+    pub fn default_tabs(&self) -> Option<Vec<usize>> {
+        let videos: Vec<usize> = self
+            .playlists()
+            .filter(|(_, sub)| sub.tab() == "videos")
+            .map(|(n, _)| n)
+            .collect();
+        (!videos.is_empty()).then_some(videos)
+    }
+
+    /// The entry numbers of the nested playlists picked by `spec`: tab names
+    /// (`videos`, `shorts`, `live`, `playlists`) or titles, comma-separated,
+    /// or a selection of entry numbers like `"1,3"`.
+    pub fn choose(&self, spec: &str) -> Result<Vec<usize>> {
+        let chosen: Vec<usize> = match parse_ranges(spec, self.entries.len()) {
+            Ok(numbers) => self
+                .playlists()
+                .map(|(n, _)| n)
+                .filter(|n| numbers.contains(n))
+                .collect(),
+            Err(_) => {
+                let mut chosen = Vec::new();
+                for name in spec.split(',') {
+                    let before = chosen.len();
+                    chosen.extend(
+                        self.playlists()
+                            .filter(|(_, sub)| sub.is_named(name))
+                            .map(|(n, _)| n),
+                    );
+                    if chosen.len() == before {
+                        return Err(AppError::UnknownTab(name.trim().to_string()));
+                    }
+                }
+                chosen
+            }
+        };
+        if chosen.is_empty() {
+            return Err(AppError::UnknownTab(spec.trim().to_string()));
         }
+        Ok(chosen)
+    }
+}
+
+impl SubPlaylist {
+    /// Whether `name` is its tab (the last part of its URL, with `live` for
+    /// `streams`) or its title, in full or after the channel name.
+    fn is_named(&self, name: &str) -> bool {
+        let name = name.trim().to_lowercase();
+        let tab = self.tab();
+        let title = self.title.to_lowercase();
+        tab == name
+            || (name == "live" && tab == "streams")
+            || title == name
+            || title.ends_with(&format!(" - {name}"))
+    }
+
+    /// The last part of its URL, lowercased.
+    fn tab(&self) -> String {
+        let tab = self.url.trim_end_matches('/').rsplit('/').next().unwrap_or_default();
+        tab.split('?').next().unwrap_or_default().to_lowercase()
+    }
+}
+
+/// How deep nested playlists are followed: the tabs of a channel, then the
+/// playlists of its Playlists tab.
+const MAX_DEPTH: usize = 2;
+
+fn to_listing(title: Option<String>, entries: Vec<YtDlpEntry>) -> Listing {
+    Listing {
+        title,
+        entries: entries
+            .into_iter()
+            .enumerate()
+            .map(|(i, e)| to_entry(i + 1, e))
+            .collect(),
+    }
+}
 
-        let json: YtDlpPlaylistJson = serde_json::from_slice(&output.stdout)?;
+fn to_entry(index: usize, e: YtDlpEntry) -> ListEntry {
+    if e.is_playlist() {
+        let title = e.title.unwrap_or_else(|| format!("Playlist {index}"));
+        return ListEntry::Playlist(SubPlaylist {
+            entries: e.entries.map(|entries| to_listing(Some(title.clone()), entries)),
+            title,
+            url: e.url.or(e.webpage_url).unwrap_or_default(),
+        });
+    }
+
+    let upload_date = e.upload_date();
+    let id = e.id.unwrap_or_default();
+    ListEntry::Video(VideoInfo {
+        index,
+        url: e
+            .url
+            .unwrap_or_else(|| format!("https://www.youtube.com/watch?v={id}")),
+        id: id.clone(),
+        title: e.title.unwrap_or_else(|| format!("Video {index}")),
+        duration: e.duration,
+        upload_date,
+        chapters: Vec::new(),
+        selected_sections: Vec::new(),
+        removed_sections: Vec::new(),
+        sizes: SizeInfo::default(),
+        position: Vec::new(),
+    })
+}
+
+impl YtDlpFetcher {
+    /// List a playlist, channel or video without expanding nested playlists.
+    pub fn list(&self, url: &str) -> Result<Listing> {
+        let stdout = self.run(&["--flat-playlist", "-J", "--no-warnings", url])?;
+        let json: YtDlpPlaylistJson = serde_json::from_slice(&stdout)?;
 
         // Check if this is a playlist with entries
         if let Some(entries) = json.entries {
             if entries.is_empty() {
                 return Err(AppError::EmptyPlaylist);
             }
+            return Ok(to_listing(json.title, entries));
+        }
 
-            let videos: Vec<VideoInfo> = entries
-                .into_iter()
-                .enumerate()
-                .map(|(i, e)| {
-                    let id = e.id.unwrap_or_default();
-                    VideoInfo {
-                        index: i + 1,
-                        url: e
-                            .url
-                            .unwrap_or_else(|| format!("https://www.youtube.com/watch?v={id}")),
-                        id: id.clone(),
-                        title: e.title.unwrap_or_else(|| format!("Video {}", i + 1)),
-                        duration: e.duration,
-                        chapters: Vec::new(),
-                        selected_sections: Vec::new(),
+        // Single video — chapters may be available directly
+        let id = json.id.unwrap_or_default();
+        let video = VideoInfo {
+            index: 1,
+            url: json
+                .webpage_url
+                .unwrap_or_else(|| format!("https://www.youtube.com/watch?v={id}")),
+            id: id.clone(),
+            title: json.full_title.unwrap_or_else(|| "Unknown".to_string()),
+            duration: json.duration,
+            upload_date: json.upload_date,
+            chapters: json.chapters.unwrap_or_default(),
+            selected_sections: Vec::new(),
+            removed_sections: Vec::new(),
+            sizes: json.sizes.size_info(),
+            position: Vec::new(),
+        };
+        Ok(Listing {
+            title: None,
+            entries: vec![ListEntry::Video(video)],
+        })
+    }
+
+    /// The videos of `listing`, numbered from 1, with the nested playlists
+    /// whose entry numbers are in `chosen` (all if `None`) listed and
+    /// flattened in. Their videos keep their position in `position`; a video
+    /// listed more than once is kept where it first appears.
+    pub fn flatten(
+        &self,
+        listing: Listing,
+        chosen: Option<&[usize]>,
+    ) -> Result<(Option<String>, Vec<VideoInfo>)> {
+        let nested = listing.has_playlists();
+        let title = listing.title.clone();
+        let mut videos = Vec::new();
+        self.flatten_into(listing, chosen, &[], 0, &mut false, &mut videos);
+        let listed = videos.len();
+        let mut seen = HashSet::new();
+        videos.retain(|v| v.id.is_empty() || seen.insert(v.id.clone()));
+        if videos.len() < listed {
+            log::info!("Dropped {} duplicate video(s)", listed - videos.len());
+        }
+        if videos.is_empty() {
+            return Err(AppError::EmptyPlaylist);
+        }
+        for (i, video) in videos.iter_mut().enumerate() {
+            video.index = i + 1;
+            if !nested {
+                video.position.clear();
+            }
+        }
+        Ok((title, videos))
+    }
+
+    fn flatten_into(
+        &self,
+        listing: Listing,
+        chosen: Option<&[usize]>,
+        parent: &[usize],
+        depth: usize,
+        listed: &mut bool,
+        out: &mut Vec<VideoInfo>,
+    ) {
+        for (i, entry) in listing.entries.into_iter().enumerate() {
+            let mut position = parent.to_vec();
+            position.push(i + 1);
+            let sub = match entry {
+                ListEntry::Video(mut video) => {
+                    video.position = position;
+                    out.push(video);
+                    continue;
+                }
+                ListEntry::Playlist(sub) => sub,
+            };
+            if chosen.is_some_and(|chosen| !chosen.contains(&(i + 1))) {
+                continue;
+            }
+            if depth >= MAX_DEPTH {
+                log::warn!("Skipping \"{}\": playlists nested too deep", sub.title);
+                continue;
+            }
+            // A tab that fails to list (e.g. a channel without shorts) is
+            // skipped rather than failing the others
+            let inner = match sub.entries {
+                Some(inner) => inner,
+                None => {
+                    if let Some(delay) = self.sleep_requests.filter(|_| *listed) {
+                        thread::sleep(delay);
+                    }
+                    *listed = true;
+                    match self.list(&sub.url) {
+                        Ok(inner) => inner,
+                        Err(e) => {
+                            log::warn!("Skipping \"{}\": {e}", sub.title);
+                            continue;
+                        }
                     }
-                })
-                .collect();
-
-            Ok((json.title, videos))
-        } else {
-            // Single video — chapters may be available directly
-            let id = json.id.unwrap_or_default();
-            let video = VideoInfo {
-                index: 1,
-                url: json
-                    .webpage_url
-                    .unwrap_or_else(|| format!("https://www.youtube.com/watch?v={id}")),
-                id: id.clone(),
-                title: json.full_title.unwrap_or_else(|| "Unknown".to_string()),
-                duration: json.duration,
-                chapters: json.chapters.unwrap_or_default(),
-                selected_sections: Vec::new(),
+                }
             };
-            Ok((None, vec![video]))
+            self.flatten_into(inner, None, &position, depth + 1, listed, out);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel() -> Listing {
+        let json = r#"{
+            "title": "Chan",
+            "entries": [
+                {"_type": "url", "ie_key": "YoutubeTab", "title": "Chan - Videos",
+                 "url": "https://www.youtube.com/@chan/videos"},
+                {"_type": "url", "ie_key": "YoutubeTab", "title": "Chan - Live",
+                 "url": "https://www.youtube.com/@chan/streams"},
+                {"_type": "playlist", "title": "Chan - Shorts", "entries": [
+                    {"_type": "url", "ie_key": "Youtube", "id": "s1", "title": "Short"}
+                ]}
+            ]
+        }"#;
+        let json: YtDlpPlaylistJson = serde_json::from_str(json).unwrap();
+        to_listing(json.title, json.entries.unwrap())
+    }
+
+    #[test]
+    fn test_nested_entries() {
+        let listing = channel();
+        let numbers: Vec<usize> = listing.playlists().map(|(n, _)| n).collect();
+        assert_eq!(numbers, vec![1, 2, 3]);
+
+        // Listed entries are flattened without running yt-dlp
+        let (title, videos) = YtDlpFetcher::default().flatten(listing, Some(&[3])).unwrap();
+        assert_eq!(title.as_deref(), Some("Chan"));
+        assert_eq!(videos.len(), 1);
+        assert_eq!(videos[0].index, 1);
+        assert_eq!(videos[0].position_display().as_deref(), Some("3.1"));
+    }
+
+    #[test]
+    fn test_choose_tabs() {
+        let listing = channel();
+        assert_eq!(listing.choose("videos").unwrap(), vec![1]);
+        assert_eq!(listing.choose("live,Chan - Shorts").unwrap(), vec![2, 3]);
+        assert_eq!(listing.choose("2-3").unwrap(), vec![2, 3]);
+        assert_eq!(listing.choose("all").unwrap(), vec![1, 2, 3]);
+        assert!(matches!(listing.choose("podcasts"), Err(AppError::UnknownTab(_))));
+    }
+
+    #[test]
+    fn test_default_tabs() {
+        assert_eq!(channel().default_tabs(), Some(vec![1]));
+
+        // A Playlists tab has no Videos tab, even with a playlist titled so
+        let json = r#"{
+            "title": "Chan - Playlists",
+            "entries": [
+                {"_type": "url", "ie_key": "YoutubeTab", "title": "Videos",
+                 "url": "https://www.youtube.com/playlist?list=PL1"},
+                {"_type": "url", "ie_key": "YoutubeTab", "title": "Mix",
+                 "url": "https://www.youtube.com/playlist?list=PL2"}
+            ]
+        }"#;
+        let json: YtDlpPlaylistJson = serde_json::from_str(json).unwrap();
+        let listing = to_listing(json.title, json.entries.unwrap());
+        assert_eq!(listing.default_tabs(), None);
+    }
+
+    #[test]
+    fn test_flatten_dedupes_by_id() {
+        let json = r#"{
+            "title": "Chan - Playlists",
+            "entries": [
+                {"_type": "playlist", "title": "First", "entries": [
+                    {"_type": "url", "ie_key": "Youtube", "id": "a", "title": "A"},
+                    {"_type": "url", "ie_key": "Youtube", "id": "b", "title": "B"}
+                ]},
+                {"_type": "playlist", "title": "Second", "entries": [
+                    {"_type": "url", "ie_key": "Youtube", "id": "b", "title": "B"},
+                    {"_type": "url", "ie_key": "Youtube", "id": "c", "title": "C"}
+                ]}
+            ]
+        }"#;
+        let json: YtDlpPlaylistJson = serde_json::from_str(json).unwrap();
+        let listing = to_listing(json.title, json.entries.unwrap());
+        let (_, videos) = YtDlpFetcher::default().flatten(listing, None).unwrap();
+        let ids: Vec<&str> = videos.iter().map(|v| v.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b", "c"]);
+        assert_eq!(videos[1].position_display().as_deref(), Some("1.2"));
+        assert_eq!(videos[2].index, 3);
+    }
+}
@@ -0,0 +1,215 @@
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Instant;
+
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use tokio_util::sync::CancellationToken;
+
+use super::process::{self, command_line};
+
+use crate::error::{AppError, Result};
+use crate::model::audio::AudioProcessing;
+use crate::model::output::OutputFile;
+
+/// Re-encode `file` in place through the ffmpeg filters in `settings`.
+///
+/// ffmpeg writes to a `.temp.` file next to the original, which replaces it
+/// only on success; `on_progress` gets percentages when the duration is
+/// known. The file's size is updated afterwards.
+pub async fn process_audio(
+    file: &mut OutputFile,
+    settings: &AudioProcessing,
+    is_clip: bool,
+    on_progress: &(dyn Fn(f64) + Send + Sync),
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let temp = temp_path(&file.path);
+    let args = ffmpeg_args(&file.path, &temp, settings, is_clip, file.duration);
+
+    let result = run_ffmpeg(&args, file.duration, on_progress, cancel).await;
+    if let Err(e) = result {
+        let _ = tokio::fs::remove_file(&temp).await;
+        return Err(match e {
+            FfmpegError::Cancelled => AppError::Cancelled,
+            FfmpegError::Failed(reason) => AppError::PostProcess {
+                path: file.path.clone(),
+                reason,
+            },
+        });
+    }
+
+    tokio::fs::rename(&temp, &file.path).await?;
+    file.size = tokio::fs::metadata(&file.path).await.ok().map(|m| m.len());
+    Ok(())
+}
+
+/// `Song.mp3` -> `Song.temp.mp3`, matched by `remove_partial_files`.
+fn temp_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{stem}.temp.{}", ext.to_string_lossy()),
+        None => format!("{stem}.temp"),
+    };
+    path.with_file_name(name)
+}
+
+fn ffmpeg_args(
+    input: &Path,
+    output: &Path,
+    settings: &AudioProcessing,
+    is_clip: bool,
+    duration: Option<f64>,
+) -> Vec<String> {
+    let ext = input
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let mut args: Vec<String> = ["-hide_banner", "-nostats", "-loglevel", "error", "-y", "-i"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    args.push(input.to_string_lossy().to_string());
+    args.extend(["-map".to_string(), "0:a".to_string()]);
+
+    let filters = settings.filters(is_clip, duration);
+    if !filters.is_empty() {
+        args.push("-af".to_string());
+        args.push(filters.join(","));
+    }
+    if settings.mono {
+        args.extend(["-ac".to_string(), "1".to_string()]);
+    }
+    // loudnorm resamples to 192 kHz internally, so always pin the output rate
+    // when normalizing
+    let default_rate = if ext == "opus" { 48000 } else { 44100 };
+    let rate = settings
+        .sample_rate
+        .or(settings.normalize.map(|_| default_rate));
+    if let Some(rate) = rate {
+        args.extend(["-ar".to_string(), rate.to_string()]);
+    }
+
+    // Keep quality close to what yt-dlp produced
+    let codec: &[&str] = match ext.as_str() {
+        "mp3" => &["-c:a", "libmp3lame", "-q:a", "0"],
+        "opus" => &["-c:a", "libopus", "-b:a", "160k"],
+        "m4a" => &["-c:a", "aac", "-b:a", "256k"],
+        _ => &[],
+    };
+    args.extend(codec.iter().map(|s| s.to_string()));
+
+    args.extend(["-progress".to_string(), "pipe:1".to_string()]);
+    args.push(output.to_string_lossy().to_string());
+    args
+}
+
+enum FfmpegError {
+    Cancelled,
+    Failed(String),
+}
+
+async fn run_ffmpeg(
+    args: &[String],
+    duration: Option<f64>,
+    on_progress: &(dyn Fn(f64) + Send + Sync),
+    cancel: &CancellationToken,
+) -> std::result::Result<(), FfmpegError> {
+    log::info!("Running {}", command_line("ffmpeg", args));
+    let started = Instant::now();
+    let mut child = process::make_cancellable(&mut Command::new("ffmpeg"))
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            FfmpegError::Failed(match e.kind() {
+                std::io::ErrorKind::NotFound => "ffmpeg not found".to_string(),
+                _ => format!("could not run ffmpeg: {e}"),
+            })
+        })?;
+    let pid = child.id();
+    let _group = process::track(pid);
+
+    let stderr_handle = {
+        let stderr = child.stderr.take();
+        tokio::spawn(async move {
+            let mut err_lines = Vec::new();
+            if let Some(stderr) = stderr {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    err_lines.push(line);
+                }
+            }
+            err_lines
+        })
+    };
+
+    let stdout = child.stdout.take();
+    let work = async {
+        if let Some(stdout) = stdout {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if let Some(percent) = parse_progress(&line, duration) {
+                    on_progress(percent);
+                }
+            }
+        }
+        child.wait().await
+    };
+    tokio::pin!(work);
+
+    let status = tokio::select! {
+        status = &mut work => status.map_err(|e| FfmpegError::Failed(e.to_string()))?,
+        _ = cancel.cancelled() => {
+            process::terminate(pid);
+            if tokio::time::timeout(process::TERMINATE_TIMEOUT, &mut work).await.is_err() {
+                process::kill(pid);
+            }
+            return Err(FfmpegError::Cancelled);
+        }
+    };
+
+    let stderr_lines = stderr_handle.await.unwrap_or_default();
+    log::info!("ffmpeg finished ({status}) in {:.1?}", started.elapsed());
+    if !stderr_lines.is_empty() {
+        log::debug!("ffmpeg stderr:\n{}", stderr_lines.join("\n"));
+    }
+    if status.success() {
+        return Ok(());
+    }
+    let reason = stderr_lines
+        .iter()
+        .rfind(|l| !l.trim().is_empty())
+        .cloned()
+        .unwrap_or_else(|| format!("ffmpeg exited with {status}"));
+    Err(FfmpegError::Failed(reason))
+}
+
+/// Percentage from an ffmpeg `-progress` line like `out_time_us=12345678`.
+fn parse_progress(line: &str, duration: Option<f64>) -> Option<f64> {
+    let duration = duration.filter(|d| *d > 0.0)?;
+    let micros: f64 = line.strip_prefix("out_time_us=")?.trim().parse().ok()?;
+    Some((micros / 1_000_000.0 / duration * 100.0).clamp(0.0, 100.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_temp_path() {
+        assert_eq!(
+            temp_path(Path::new("/music/Song - Intro.mp3")),
+            PathBuf::from("/music/Song - Intro.temp.mp3")
+        );
+    }
+
+    #[test]
+    fn test_parse_progress() {
+        assert_eq!(parse_progress("out_time_us=30000000", Some(60.0)), Some(50.0));
+        assert_eq!(parse_progress("out_time_us=30000000", None), None);
+        assert_eq!(parse_progress("speed=2.5x", Some(60.0)), None);
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::BTreeSet;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use tokio::process::Command;
+
+use crate::error::AppError;
+
+/// How long a child gets to exit after being asked to terminate.
+pub const TERMINATE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Prepare a command for a cancellable child process.
+///
+/// On Unix the child gets its own process group: a terminal Ctrl-C then only
+/// reaches yt-grab, and we can signal the child together with any helpers it
+/// spawns (yt-dlp runs ffmpeg). The child is killed if its handle is dropped;
+/// `track` the spawned child so that a force quit kills it too.
+pub fn make_cancellable(cmd: &mut Command) -> &mut Command {
+    #[cfg(unix)]
+    cmd.process_group(0);
+    cmd.kill_on_drop(true)
+}
+
+/// Process groups of the running children started with `make_cancellable`.
+static GROUPS: Mutex<BTreeSet<u32>> = Mutex::new(BTreeSet::new());
+
+fn groups() -> MutexGuard<'static, BTreeSet<u32>> {
+    GROUPS.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Keeps a child's process group listed for `kill_all` until dropped.
+pub struct TrackedGroup(Option<u32>);
+
+impl Drop for TrackedGroup {
+    fn drop(&mut self) {
+        if let Some(pid) = self.0 {
+            groups().remove(&pid);
+        }
+    }
+}
+
+/// List the process group of a child started with `make_cancellable` for
+/// `kill_all`, while the returned guard lives.
+pub fn track(pid: Option<u32>) -> TrackedGroup {
+    if let Some(pid) = pid {
+        groups().insert(pid);
+    }
+    TrackedGroup(pid)
+}
+
+/// Forcefully kill every tracked child and its helpers, before a force quit:
+/// `std::process::exit` skips the kill on drop, and the terminal's Ctrl-C
+/// doesn't reach the children's own process groups.
+pub fn kill_all() {
+    let pids: Vec<u32> = groups().iter().copied().collect();
+    for pid in pids {
+        kill(Some(pid));
+    }
+}
+
+/// Map a failure to start `program`: a missing executable becomes
+/// `AppError::BackendMissing`.
+pub fn spawn_error(program: &'static str, error: std::io::Error) -> AppError {
+    match error.kind() {
+        std::io::ErrorKind::NotFound => AppError::BackendMissing(program),
+        _ => AppError::Io(error),
+    }
+}
+
+/// Quote an argument for display as a POSIX shell word.
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=,+%@".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// A command line for log messages and `--dry-run`.
+pub fn command_line<S: AsRef<str>>(program: &str, args: &[S]) -> String {
+    std::iter::once(program)
+        .chain(args.iter().map(AsRef::as_ref))
+        .map(shell_quote)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Ask a child started with `make_cancellable` (and its helpers) to exit.
+pub fn terminate(pid: Option<u32>) {
+    #[cfg(unix)]
+    signal_group(pid, libc::SIGTERM);
+    // Elsewhere, dropping the handle kills the child
+    #[cfg(not(unix))]
+    let _ = pid;
+}
+
+/// Forcefully kill a child started with `make_cancellable` and its helpers.
+pub fn kill(pid: Option<u32>) {
+    #[cfg(unix)]
+    signal_group(pid, libc::SIGKILL);
+    #[cfg(not(unix))]
+    let _ = pid;
+}
+
+#[cfg(unix)]
+fn signal_group(pid: Option<u32>, signal: libc::c_int) {
+    let Some(pid) = pid.and_then(|p| libc::pid_t::try_from(p).ok()) else {
+        return;
+    };
+    // SAFETY: kill(2) with a negative pid signals the process group led by
+    // the child; it has no memory-safety preconditions.
+    unsafe {
+        libc::kill(-pid, signal);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_line_quoting() {
+        assert_eq!(
+            command_line("yt-dlp", &["-o", "./%(title)s.%(ext)s", "https://youtu.be/x?t=1"]),
+            "yt-dlp -o './%(title)s.%(ext)s' 'https://youtu.be/x?t=1'"
+        );
+        assert_eq!(
+            command_line("ffmpeg", &["-i", "It's a song.mp3", "", "a=b,c"]),
+            r"ffmpeg -i 'It'\''s a song.mp3' '' a=b,c"
+        );
+    }
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_kill_all_kills_tracked_groups() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let mut child = make_cancellable(&mut Command::new("sh"))
+            .args(["-c", "sleep 30 & wait"])
+            .spawn()
+            .unwrap();
+        let pid = child.id().unwrap();
+        let group = track(Some(pid));
+        assert!(groups().contains(&pid));
+        kill_all();
+        let status = child.wait().await.unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGKILL));
+
+        drop(group);
+        assert!(!groups().contains(&pid));
+    }
+}
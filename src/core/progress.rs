@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use serde::Serialize;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+/// Download lifecycle updates emitted by `download_all`, keyed by the
+/// position of the item in the batch.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    Queued { item: usize, id: String, title: String },
+    /// Not started yet because of `reason` (e.g. low disk space)
+    Paused { item: usize, reason: String },
+    /// Waiting to start again after being paused
+    Resumed { item: usize },
+    /// The download started
+    Started { item: usize },
+    /// The download moved on to another phase; later `Progress` events
+    /// refer to it
+    Phase { item: usize, phase: Phase },
+    Progress { item: usize, percent: f64 },
+    /// yt-dlp is retrying a failed request or fragment
+    Retry { item: usize, message: String },
+    Finished { item: usize },
+    Failed { item: usize, error: String },
+    Cancelled { item: usize },
+}
+
+/// Stages of an item after `Started` (which is the download itself).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    /// yt-dlp's own post-processing: audio extraction, merging, remuxing
+    Converting,
+    /// The ffmpeg audio processing stage (`--normalize` etc.), with progress
+    Processing,
+}
+
+pub trait ProgressReporter: Send + Sync {
+    fn report(&self, event: ProgressEvent);
+}
+
+#[derive(Clone)]
+enum Subscriber {
+    Channel(UnboundedSender<ProgressEvent>),
+    Reporter(Arc<dyn ProgressReporter>),
+}
+
+/// Delivers every `ProgressEvent` of a run to any number of subscribers:
+/// channels for consumers with their own event loop, and reporters called in
+/// place. Clones share the subscriber list.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+}
+
+impl EventBus {
+    /// Receive all events sent from now on. Dropping the receiver
+    /// unsubscribes.
+    pub fn subscribe(&self) -> UnboundedReceiver<ProgressEvent> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.subscribers.lock().unwrap().push(Subscriber::Channel(tx));
+        rx
+    }
+
+    /// Call `reporter` with all events sent from now on. It runs on the
+    /// sending task, so it must not block.
+    pub fn attach(&self, reporter: Arc<dyn ProgressReporter>) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .push(Subscriber::Reporter(reporter));
+    }
+
+    pub fn send(&self, event: ProgressEvent) {
+        // Reporters run without the lock, so one that is slow or uses the
+        // bus itself doesn't hold up (or deadlock) other senders
+        let subscribers = self.subscribers.lock().unwrap().clone();
+        let mut closed = false;
+        for sub in &subscribers {
+            match sub {
+                Subscriber::Channel(tx) => closed |= tx.send(event.clone()).is_err(),
+                Subscriber::Reporter(reporter) => reporter.report(event.clone()),
+            }
+        }
+        if closed {
+            self.subscribers
+                .lock()
+                .unwrap()
+                .retain(|sub| !matches!(sub, Subscriber::Channel(tx) if tx.is_closed()));
+        }
+    }
+
+    /// A sender for the events of one item.
+    pub fn item(&self, item: usize) -> ItemEvents {
+        ItemEvents {
+            item,
+            bus: self.clone(),
+        }
+    }
+}
+
+/// The events a `Downloader` emits for the item it is working on.
+#[derive(Clone)]
+pub struct ItemEvents {
+    item: usize,
+    bus: EventBus,
+}
+
+impl ItemEvents {
+    pub fn progress(&self, percent: f64) {
+        self.bus.send(ProgressEvent::Progress {
+            item: self.item,
+            percent,
+        });
+    }
+
+    pub fn phase(&self, phase: Phase) {
+        self.bus.send(ProgressEvent::Phase {
+            item: self.item,
+            phase,
+        });
+    }
+
+    pub fn retry(&self, message: String) {
+        self.bus.send(ProgressEvent::Retry {
+            item: self.item,
+            message,
+        });
+    }
+}
+
+/// Renders one indicatif progress bar per item.
+pub struct BarReporter {
+    multi: MultiProgress,
+    style: ProgressStyle,
+    bars: Mutex<HashMap<usize, ProgressBar>>,
+}
+
+impl BarReporter {
+    pub fn new(multi: MultiProgress) -> Self {
+        let style = ProgressStyle::with_template("  {prefix:.cyan} [{bar:30.green/dim}] {msg}")
+            .unwrap()
+            .progress_chars("━╸─");
+
+        Self {
+            multi,
+            style,
+            bars: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn with_bar(&self, item: usize, f: impl FnOnce(&ProgressBar)) {
+        if let Some(pb) = self.bars.lock().unwrap().get(&item) {
+            f(pb);
+        }
+    }
+}
+
+impl ProgressReporter for BarReporter {
+    fn report(&self, event: ProgressEvent) {
+        match event {
+            ProgressEvent::Queued { item, title, .. } => {
+                let pb = self.multi.add(ProgressBar::new(100));
+                pb.set_style(self.style.clone());
+                pb.set_prefix(short_title(&title));
+                pb.set_message("waiting...");
+                self.bars.lock().unwrap().insert(item, pb);
+            }
+            ProgressEvent::Paused { item, reason } => {
+                self.with_bar(item, |pb| pb.set_message(format!("paused: {reason}")));
+            }
+            ProgressEvent::Resumed { item } => {
+                self.with_bar(item, |pb| pb.set_message("waiting..."));
+            }
+            ProgressEvent::Started { item } => {
+                self.with_bar(item, |pb| pb.set_message("downloading"));
+            }
+            ProgressEvent::Phase { item, phase } => {
+                self.with_bar(item, |pb| match phase {
+                    Phase::Converting => pb.set_message("converting"),
+                    Phase::Processing => {
+                        pb.set_position(0);
+                        pb.set_message("processing");
+                    }
+                });
+            }
+            ProgressEvent::Retry { item, .. } => {
+                self.with_bar(item, |pb| pb.set_message("retrying"));
+            }
+            ProgressEvent::Progress { item, percent } => {
+                self.with_bar(item, |pb| {
+                    if pb.message() == "retrying" {
+                        pb.set_message("downloading");
+                    }
+                    pb.set_position(percent as u64);
+                });
+            }
+            ProgressEvent::Finished { item } => {
+                self.with_bar(item, |pb| {
+                    pb.set_position(100);
+                    pb.finish_with_message("done ✓");
+                });
+            }
+            ProgressEvent::Failed { item, .. } => {
+                self.with_bar(item, |pb| pb.finish_with_message("FAILED ✗"));
+            }
+            ProgressEvent::Cancelled { item } => {
+                self.with_bar(item, |pb| pb.abandon_with_message("cancelled"));
+            }
+        }
+    }
+}
+
+/// Calls a closure with each event.
+pub struct CallbackReporter<F>(pub F);
+
+impl<F: Fn(ProgressEvent) + Send + Sync> ProgressReporter for CallbackReporter<F> {
+    fn report(&self, event: ProgressEvent) {
+        (self.0)(event);
+    }
+}
+
+/// Prints plain log lines instead of progress bars, for non-interactive runs
+/// where stdout may be a file or pipe.
+#[derive(Default)]
+pub struct LogReporter {
+    items: Mutex<HashMap<usize, LogItem>>,
+}
+
+struct LogItem {
+    title: String,
+    /// Last reported quarter (0-4), so progress is logged at 25% steps
+    quarter: u8,
+}
+
+impl LogReporter {
+    fn log(&self, item: usize, f: impl FnOnce(&mut LogItem) -> Option<String>) {
+        let mut items = self.items.lock().unwrap();
+        // Items can be added while the batch runs
+        let total = items.len();
+        if let Some(it) = items.get_mut(&item) {
+            if let Some(msg) = f(it) {
+                println!("[{}/{total}] {}: {msg}", item + 1, it.title);
+            }
+        }
+    }
+}
+
+impl ProgressReporter for LogReporter {
+    fn report(&self, event: ProgressEvent) {
+        match event {
+            ProgressEvent::Queued { item, title, .. } => {
+                self.items
+                    .lock()
+                    .unwrap()
+                    .insert(item, LogItem { title, quarter: 0 });
+            }
+            ProgressEvent::Paused { item, reason } => {
+                self.log(item, |_| Some(format!("paused: {reason}")))
+            }
+            ProgressEvent::Resumed { item } => self.log(item, |_| Some("resumed".into())),
+            ProgressEvent::Started { item } => self.log(item, |_| Some("started".into())),
+            ProgressEvent::Phase { item, phase } => self.log(item, |it| match phase {
+                Phase::Converting => Some("converting".into()),
+                Phase::Processing => {
+                    it.quarter = 0;
+                    Some("processing".into())
+                }
+            }),
+            ProgressEvent::Progress { item, percent } => self.log(item, |it| {
+                let quarter = (percent / 25.0).floor().clamp(0.0, 3.0) as u8;
+                (quarter > it.quarter).then(|| {
+                    it.quarter = quarter;
+                    format!("{}%", u32::from(quarter) * 25)
+                })
+            }),
+            ProgressEvent::Retry { item, message } => {
+                self.log(item, |_| Some(format!("retrying: {message}")))
+            }
+            ProgressEvent::Finished { item } => self.log(item, |_| Some("done".into())),
+            ProgressEvent::Failed { item, error } => {
+                self.log(item, |_| Some(format!("FAILED: {error}")))
+            }
+            ProgressEvent::Cancelled { item } => self.log(item, |_| Some("cancelled".into())),
+        }
+    }
+}
+
+/// Truncate a title for display in a fixed-width column.
+pub fn short_title(title: &str) -> String {
+    if title.chars().count() > 40 {
+        let head: String = title.chars().take(37).collect();
+        format!("{head}...")
+    } else {
+        title.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_bus_fan_out() {
+        let bus = EventBus::default();
+        let mut first = bus.subscribe();
+        let second = bus.subscribe();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        bus.attach(Arc::new(CallbackReporter(move |e| sink.lock().unwrap().push(e))));
+
+        drop(second);
+        bus.item(2).progress(50.0);
+
+        let expected = ProgressEvent::Progress {
+            item: 2,
+            percent: 50.0,
+        };
+        assert_eq!(first.try_recv().unwrap(), expected);
+        assert_eq!(*seen.lock().unwrap(), vec![expected]);
+        // The dropped receiver was unsubscribed
+        assert_eq!(bus.subscribers.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_reporter_can_use_the_bus() {
+        let bus = EventBus::default();
+        let inner = bus.clone();
+        let mut rx = bus.subscribe();
+        // Would deadlock if reporters were called with the lock held
+        bus.attach(Arc::new(CallbackReporter(move |e| {
+            if let ProgressEvent::Finished { item } = e {
+                inner.subscribe();
+                inner.send(ProgressEvent::Cancelled { item: item + 1 });
+            }
+        })));
+
+        bus.send(ProgressEvent::Finished { item: 0 });
+        assert_eq!(rx.try_recv().unwrap(), ProgressEvent::Finished { item: 0 });
+        assert_eq!(rx.try_recv().unwrap(), ProgressEvent::Cancelled { item: 1 });
+    }
+}
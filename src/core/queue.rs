@@ -0,0 +1,598 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+
+use super::grabber::{Grabber, GrabberBuilder};
+use super::progress::{EventBus, ProgressEvent};
+
+use crate::error::{AppError, Result};
+use crate::model::audio::AudioProcessing;
+use crate::model::config::{DownloadConfig, QueueOrder};
+use crate::model::format::MediaFormat;
+use crate::model::hook::HookConfig;
+use crate::model::video::VideoInfo;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryState {
+    Pending,
+    Active,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl EntryState {
+    fn is_final(self) -> bool {
+        matches!(self, Self::Done | Self::Failed | Self::Cancelled)
+    }
+}
+
+/// A view of one queue entry, see `DownloadQueue::entries`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueEntry {
+    pub video: VideoInfo,
+    /// Higher starts first
+    pub priority: i32,
+    /// Held back until resumed; an active download is stopped and starts
+    /// again (continuing the partial file) once resumed
+    pub paused: bool,
+    pub state: EntryState,
+}
+
+struct Slot {
+    entry: QueueEntry,
+    /// Stops the running download when the item is paused
+    cancel: Option<CancellationToken>,
+    /// The running download was stopped by `pause`, not by the batch
+    interrupted: bool,
+}
+
+/// Where the queue is saved, and the settings a `resume` needs to carry on.
+struct Store {
+    path: PathBuf,
+    config: DownloadConfig,
+}
+
+/// The queue file contents after one change, written outside the lock.
+struct Snapshot {
+    path: PathBuf,
+    revision: u64,
+    json: serde_json::Result<String>,
+}
+
+struct Inner {
+    slots: Vec<Slot>,
+    order: QueueOrder,
+    closed: bool,
+    finished: bool,
+    store: Option<Store>,
+    /// Bumped for every snapshot, so an older one never overwrites a newer
+    revision: u64,
+}
+
+impl Inner {
+    /// The next item to start: highest priority first, then by `order`.
+    fn pick(&self) -> Option<usize> {
+        let waiting = self
+            .slots
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.entry.state == EntryState::Pending && !s.entry.paused);
+        let best = match self.order {
+            QueueOrder::Playlist => {
+                waiting.max_by_key(|(i, s)| (s.entry.priority, std::cmp::Reverse(*i)))
+            }
+            QueueOrder::Shortest => waiting.max_by(|(i, a), (j, b)| {
+                let length = |s: &Slot| s.entry.video.duration.unwrap_or(f64::INFINITY);
+                a.entry
+                    .priority
+                    .cmp(&b.entry.priority)
+                    .then(length(b).total_cmp(&length(a)))
+                    .then(j.cmp(i))
+            }),
+        };
+        best.map(|(i, _)| i)
+    }
+
+    /// Nothing left to start or wait for. Paused items keep an open batch
+    /// alive until they are resumed or the batch is cancelled.
+    fn is_drained(&self) -> bool {
+        self.closed && self.slots.iter().all(|s| s.entry.state.is_final())
+    }
+
+    /// The queue file to save after a change, if the queue is persisted.
+    fn snapshot(&mut self) -> Option<Snapshot> {
+        let store = self.store.as_ref()?;
+        let file = QueueFile {
+            format: store.config.format.extension().to_string(),
+            output_dir: store.config.output_dir.clone(),
+            order: self.order,
+            settings: Settings::new(&store.config),
+            entries: self.slots.iter().map(|s| s.entry.clone()).collect(),
+        };
+        let json = serde_json::to_string_pretty(&file);
+        let path = store.path.clone();
+        self.revision += 1;
+        Some(Snapshot {
+            path,
+            revision: self.revision,
+            json,
+        })
+    }
+}
+
+/// The items of a batch waiting to be downloaded. Workers take the next item
+/// with `next`; meanwhile items can be added, paused, resumed and
+/// reprioritized from other tasks. Clones share the queue.
+#[derive(Clone)]
+pub struct DownloadQueue {
+    inner: Arc<Mutex<Inner>>,
+    changed: Arc<Notify>,
+    events: EventBus,
+    /// Revision of the last snapshot written to the queue file
+    saved: Arc<Mutex<u64>>,
+}
+
+impl DownloadQueue {
+    /// An empty queue sending `Queued`, `Paused` and `Resumed` events on
+    /// `events`.
+    pub fn new(order: QueueOrder, events: EventBus) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                slots: Vec::new(),
+                order,
+                closed: false,
+                finished: false,
+                store: None,
+                revision: 0,
+            })),
+            changed: Arc::new(Notify::new()),
+            events,
+            saved: Arc::new(Mutex::new(0)),
+        }
+    }
+
+    /// Apply `f` to the queue, wake the workers, then save it.
+    fn update<T>(&self, f: impl FnOnce(&mut Inner) -> T) -> T {
+        let mut inner = self.inner.lock().unwrap();
+        let result = f(&mut inner);
+        let snapshot = inner.snapshot();
+        drop(inner);
+        self.changed.notify_waiters();
+        self.save(snapshot);
+        result
+    }
+
+    /// Write a snapshot through a temporary file renamed over the queue
+    /// file, so an interrupted write can't leave it truncated. Snapshots
+    /// overtaken by a newer one while waiting are dropped.
+    fn save(&self, snapshot: Option<Snapshot>) {
+        let Some(snapshot) = snapshot else {
+            return;
+        };
+        let mut saved = self.saved.lock().unwrap();
+        if snapshot.revision <= *saved {
+            return;
+        }
+        let mut temp = snapshot.path.clone().into_os_string();
+        temp.push(".tmp");
+        let written = snapshot.json.map_err(AppError::from).and_then(|json| {
+            std::fs::write(&temp, json)?;
+            Ok(std::fs::rename(&temp, &snapshot.path)?)
+        });
+        match written {
+            Ok(()) => *saved = snapshot.revision,
+            Err(e) => log::warn!(
+                "Could not save the queue to {}: {e}",
+                snapshot.path.display()
+            ),
+        }
+    }
+
+    /// Save the queue to `path` now and after every change, so an
+    /// interrupted batch can be picked up again with `SavedQueue`. The
+    /// download settings in `config` are saved too, except network settings.
+    pub fn persist(&self, path: impl Into<PathBuf>, config: &DownloadConfig) {
+        self.update(|inner| {
+            inner.store = Some(Store {
+                path: path.into(),
+                config: config.clone(),
+            });
+        });
+    }
+
+    /// Add a video, returning its item number, or `None` once the batch has
+    /// finished.
+    pub fn push(&self, video: VideoInfo, priority: i32) -> Option<usize> {
+        self.update(|inner| {
+            if inner.finished {
+                return None;
+            }
+            let item = inner.slots.len();
+            // Sent under the lock, so no worker can start the item first
+            self.events.send(ProgressEvent::Queued {
+                item,
+                id: video.id.clone(),
+                title: video.title.clone(),
+            });
+            inner.slots.push(Slot {
+                entry: QueueEntry {
+                    video,
+                    priority,
+                    paused: false,
+                    state: EntryState::Pending,
+                },
+                cancel: None,
+                interrupted: false,
+            });
+            Some(item)
+        })
+    }
+
+    /// No more items are coming: workers stop once everything added so far
+    /// is done.
+    pub fn close(&self) {
+        self.update(|inner| inner.closed = true);
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.inner.lock().unwrap().closed
+    }
+
+    pub fn set_priority(&self, item: usize, priority: i32) -> bool {
+        self.update(|inner| match inner.slots.get_mut(item) {
+            Some(slot) if !slot.entry.state.is_final() => {
+                slot.entry.priority = priority;
+                true
+            }
+            _ => false,
+        })
+    }
+
+    /// Hold back a waiting item, or stop a running one. Returns false if the
+    /// item is unknown, finished or already paused.
+    pub fn pause(&self, item: usize) -> bool {
+        let paused = self.update(|inner| {
+            let slot = inner.slots.get_mut(item)?;
+            if slot.entry.paused || slot.entry.state.is_final() {
+                return None;
+            }
+            slot.entry.paused = true;
+            if let Some(cancel) = &slot.cancel {
+                slot.interrupted = true;
+                cancel.cancel();
+            }
+            Some(slot.entry.state)
+        });
+        // A running download reports the pause once it has stopped
+        if paused == Some(EntryState::Pending) {
+            self.events.send(ProgressEvent::Paused {
+                item,
+                reason: "paused".to_string(),
+            });
+        }
+        paused.is_some()
+    }
+
+    pub fn resume(&self, item: usize) -> bool {
+        let resumed = self.update(|inner| {
+            let slot = inner.slots.get_mut(item)?;
+            if !slot.entry.paused || slot.entry.state.is_final() {
+                return None;
+            }
+            slot.entry.paused = false;
+            Some(slot.entry.state)
+        });
+        if resumed == Some(EntryState::Pending) {
+            self.events.send(ProgressEvent::Resumed { item });
+        }
+        resumed.is_some()
+    }
+
+    /// A snapshot of every item, indexed by item number.
+    pub fn entries(&self) -> Vec<QueueEntry> {
+        let inner = self.inner.lock().unwrap();
+        inner.slots.iter().map(|s| s.entry.clone()).collect()
+    }
+
+    /// Wait for the next item to download, marking it active. Returns the
+    /// item number, the video and a token that fires when the item is paused
+    /// or `cancel` fires; `None` once the queue is closed and drained, or
+    /// cancelled.
+    pub async fn next(
+        &self,
+        cancel: &CancellationToken,
+    ) -> Option<(usize, VideoInfo, CancellationToken)> {
+        loop {
+            // Register before looking, so a change in between isn't missed
+            let changed = self.changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+
+            {
+                let mut inner = self.inner.lock().unwrap();
+                if cancel.is_cancelled() {
+                    return None;
+                }
+                if let Some(item) = inner.pick() {
+                    let token = cancel.child_token();
+                    let slot = &mut inner.slots[item];
+                    slot.entry.state = EntryState::Active;
+                    slot.cancel = Some(token.clone());
+                    let video = slot.entry.video.clone();
+                    let snapshot = inner.snapshot();
+                    drop(inner);
+                    self.save(snapshot);
+                    return Some((item, video, token));
+                }
+                if inner.is_drained() {
+                    inner.finished = true;
+                    drop(inner);
+                    // Let the other workers see it too
+                    self.changed.notify_waiters();
+                    return None;
+                }
+            }
+
+            tokio::select! {
+                _ = &mut changed => {}
+                _ = cancel.cancelled() => return None,
+            }
+        }
+    }
+
+    /// Record the outcome of an item returned by `next`. An item stopped by
+    /// `pause` goes back to waiting instead; returns true in that case.
+    pub fn finish(&self, item: usize, state: EntryState) -> bool {
+        let requeued = self.update(|inner| {
+            let slot = &mut inner.slots[item];
+            slot.cancel = None;
+            if std::mem::take(&mut slot.interrupted) && state == EntryState::Cancelled {
+                slot.entry.state = EntryState::Pending;
+                Some(slot.entry.paused)
+            } else {
+                slot.entry.state = state;
+                None
+            }
+        });
+        match requeued {
+            Some(true) => self.events.send(ProgressEvent::Paused {
+                item,
+                reason: "paused".to_string(),
+            }),
+            // Resumed before the download had stopped
+            Some(false) => self.events.send(ProgressEvent::Resumed { item }),
+            None => {}
+        }
+        requeued.is_some()
+    }
+
+    /// Mark every item that didn't get to run as cancelled, returning their
+    /// numbers and titles. Ends the batch.
+    pub fn cancel_pending(&self) -> Vec<(usize, String)> {
+        let cancelled: Vec<_> = self.update(|inner| {
+            inner.finished = true;
+            inner
+                .slots
+                .iter_mut()
+                .enumerate()
+                .filter(|(_, s)| !s.entry.state.is_final())
+                .map(|(item, s)| {
+                    s.entry.state = EntryState::Cancelled;
+                    (item, s.entry.video.title.clone())
+                })
+                .collect()
+        });
+        for (item, _) in &cancelled {
+            self.events.send(ProgressEvent::Cancelled { item: *item });
+        }
+        cancelled
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct QueueFile {
+    format: String,
+    output_dir: PathBuf,
+    order: QueueOrder,
+    /// Missing from files written before settings were saved
+    #[serde(default)]
+    settings: Settings,
+    entries: Vec<QueueEntry>,
+}
+
+/// The rest of a batch's `DownloadConfig`. Concurrency is chosen again on
+/// resume, and network settings are left out as they may hold credentials.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Settings {
+    rate_limit: Option<u64>,
+    /// In seconds
+    sleep_interval: Option<f64>,
+    /// In seconds
+    sleep_requests: Option<f64>,
+    min_free_space: Option<u64>,
+    audio: AudioProcessing,
+    hooks: HookConfig,
+    fail_fast: bool,
+    write_info_json: bool,
+    playlist_title: Option<String>,
+}
+
+impl Settings {
+    fn new(config: &DownloadConfig) -> Self {
+        Self {
+            rate_limit: config.rate_limit,
+            sleep_interval: config.sleep_interval.map(|d| d.as_secs_f64()),
+            sleep_requests: config.sleep_requests.map(|d| d.as_secs_f64()),
+            min_free_space: config.min_free_space,
+            audio: config.audio.clone(),
+            hooks: config.hooks.clone(),
+            fail_fast: config.fail_fast,
+            write_info_json: config.write_info_json,
+            playlist_title: config.playlist_title.clone(),
+        }
+    }
+}
+
+/// A queue saved by `DownloadQueue::persist`.
+pub struct SavedQueue {
+    pub format: MediaFormat,
+    pub output_dir: PathBuf,
+    pub order: QueueOrder,
+    /// Title of the playlist the batch came from, if any
+    pub playlist_title: Option<String>,
+    /// Every entry, including finished ones
+    pub entries: Vec<QueueEntry>,
+    settings: Settings,
+}
+
+impl SavedQueue {
+    pub fn open(path: &Path) -> Result<Self> {
+        let invalid = |reason: String| AppError::QueueFile {
+            path: path.to_path_buf(),
+            reason,
+        };
+        let json = std::fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+        let file: QueueFile = serde_json::from_str(&json).map_err(|e| invalid(e.to_string()))?;
+        let format = MediaFormat::from_extension(&file.format)
+            .ok_or_else(|| invalid(format!("unknown format \"{}\"", file.format)))?;
+        Ok(Self {
+            format,
+            output_dir: file.output_dir,
+            order: file.order,
+            playlist_title: file.settings.playlist_title.clone(),
+            entries: file.entries,
+            settings: file.settings,
+        })
+    }
+
+    /// A builder with the settings the batch was started with: format,
+    /// directory, order, limits, audio processing, hooks, `--fail-fast` and
+    /// `--write-info-json`. Concurrency and network settings are left to the
+    /// caller.
+    pub fn builder(&self) -> GrabberBuilder {
+        let seconds = |secs: Option<f64>| secs.and_then(|s| Duration::try_from_secs_f64(s).ok());
+        Grabber::builder()
+            .format(self.format)
+            .output_dir(self.output_dir.clone())
+            .order(self.order)
+            .rate_limit(self.settings.rate_limit)
+            .sleep_interval(seconds(self.settings.sleep_interval))
+            .sleep_requests(seconds(self.settings.sleep_requests))
+            .min_free_space(self.settings.min_free_space)
+            .audio(self.settings.audio.clone())
+            .hooks(self.settings.hooks.clone())
+            .fail_fast(self.settings.fail_fast)
+            .write_info_json(self.settings.write_info_json)
+    }
+
+    /// Entries still to download: waiting, interrupted while running, failed
+    /// or cancelled.
+    pub fn unfinished(&self) -> impl Iterator<Item = &QueueEntry> {
+        self.entries.iter().filter(|e| e.state != EntryState::Done)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn video(index: usize, duration: Option<f64>) -> VideoInfo {
+        VideoInfo {
+            index,
+            id: format!("id{index}"),
+            title: format!("Video {index}"),
+            duration,
+            ..Default::default()
+        }
+    }
+
+    fn start_order(queue: &DownloadQueue) -> Vec<usize> {
+        let mut order = Vec::new();
+        let mut inner = queue.inner.lock().unwrap();
+        while let Some(item) = inner.pick() {
+            inner.slots[item].entry.state = EntryState::Active;
+            order.push(item);
+        }
+        order
+    }
+
+    #[test]
+    fn test_pick_order() {
+        let queue = DownloadQueue::new(QueueOrder::Playlist, EventBus::default());
+        for (i, duration) in [Some(300.0), None, Some(60.0), Some(120.0)].into_iter().enumerate() {
+            queue.push(video(i + 1, duration), 0);
+        }
+        assert_eq!(start_order(&queue), vec![0, 1, 2, 3]);
+
+        let queue = DownloadQueue::new(QueueOrder::Shortest, EventBus::default());
+        for (i, duration) in [Some(300.0), None, Some(60.0), Some(120.0)].into_iter().enumerate() {
+            queue.push(video(i + 1, duration), 0);
+        }
+        queue.set_priority(0, 1);
+        queue.pause(2);
+        // Priority first, then shortest; the paused item is held back
+        assert_eq!(start_order(&queue), vec![0, 3, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_pause_running_item_requeues_it() {
+        let queue = DownloadQueue::new(QueueOrder::Playlist, EventBus::default());
+        queue.push(video(1, None), 0);
+        queue.close();
+        let cancel = CancellationToken::new();
+
+        let (item, _, token) = queue.next(&cancel).await.unwrap();
+        assert!(queue.pause(item));
+        assert!(token.is_cancelled());
+        assert!(queue.finish(item, EntryState::Cancelled));
+        assert_eq!(queue.entries()[0].state, EntryState::Pending);
+
+        queue.resume(item);
+        let (again, _, _) = queue.next(&cancel).await.unwrap();
+        assert_eq!(again, item);
+        assert!(!queue.finish(item, EntryState::Done));
+        assert!(queue.next(&cancel).await.is_none());
+    }
+
+    #[test]
+    fn test_saved_settings_round_trip() {
+        let path = std::env::temp_dir().join(format!("yt-grab-queue-{}.json", std::process::id()));
+        let config = Grabber::builder()
+            .format(MediaFormat::from_extension("opus").unwrap())
+            .rate_limit(Some(500_000))
+            .sleep_interval(Some(Duration::from_millis(1500)))
+            .min_free_space(Some(1 << 30))
+            .audio(AudioProcessing::profile("podcast").unwrap())
+            .hooks(HookConfig {
+                after_item: Some(crate::model::hook::Hook::parse("tag {path}").unwrap()),
+                after_batch: None,
+            })
+            .fail_fast(true)
+            .build()
+            .config()
+            .clone();
+        let queue = DownloadQueue::new(QueueOrder::Shortest, EventBus::default());
+        queue.persist(&path, &config);
+        queue.push(video(1, None), 0);
+
+        let saved = SavedQueue::open(&path).unwrap();
+        assert_eq!(saved.entries.len(), 1);
+        let restored = saved.builder().build().config().clone();
+        assert_eq!(restored.format, config.format);
+        assert_eq!(restored.order, QueueOrder::Shortest);
+        assert_eq!(restored.rate_limit, Some(500_000));
+        assert_eq!(restored.sleep_interval, Some(Duration::from_millis(1500)));
+        assert_eq!(restored.min_free_space, Some(1 << 30));
+        assert_eq!(restored.audio, config.audio);
+        assert_eq!(restored.hooks.after_item.unwrap().program(), "tag");
+        assert!(restored.fail_fast);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,77 +1,198 @@
 use crate::error::{AppError, Result};
 
-/// Parse a range string like "1,3-5,8" into a sorted, deduplicated Vec of indices.
-/// Also accepts "all" to select everything.
+/// Parse a selection string like "1,3-5,8" into a sorted, deduplicated Vec of indices.
+///
+/// Grammar (comma-separated terms, 1-based indices):
+/// - `all` — everything
+/// - `N` — a single index; `^N` counts from the end (`^1` is the last item)
+/// - `A-B` — inclusive range; `A-` and `-B` are open-ended
+/// - `A-B:S` — every S-th index of the range
+/// - `last N` / `random N` — the last N / N random items
+/// - `!term` — exclude a term (`all,!4,!7-9`); exclusions alone apply to everything
+///
+/// Shared by playlist item and chapter selection.
 pub fn parse_ranges(input: &str, max: usize) -> Result<Vec<usize>> {
-    let input = input.trim();
+    let parser = Parser { input, max };
+    let mut included = Vec::new();
+    let mut excluded = Vec::new();
+    let mut has_positive = false;
 
-    if input.eq_ignore_ascii_case("all") {
-        return Ok((1..=max).collect());
+    let mut offset = 0;
+    for raw in input.split(',') {
+        let term_offset = offset + (raw.len() - raw.trim_start().len());
+        offset += raw.len() + 1;
+
+        let term = raw.trim();
+        if term.is_empty() {
+            continue;
+        }
+
+        if let Some(rest) = term.strip_prefix('!') {
+            let rest_offset = term_offset + 1 + (rest.len() - rest.trim_start().len());
+            excluded.extend(parser.term(rest.trim(), rest_offset)?);
+        } else {
+            has_positive = true;
+            included.extend(parser.term(term, term_offset)?);
+        }
     }
 
-    let mut indices = Vec::new();
+    if !has_positive && !excluded.is_empty() {
+        included.extend(1..=max);
+    }
 
-    for part in input.split(',') {
-        let part = part.trim();
-        if part.is_empty() {
-            continue;
+    included.retain(|i| !excluded.contains(i));
+    included.sort_unstable();
+    included.dedup();
+
+    if included.is_empty() {
+        return Err(parser.error(0, "no indices selected"));
+    }
+
+    Ok(included)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    max: usize,
+}
+
+impl Parser<'_> {
+    /// Build an error pointing at a byte offset of the input (reported 1-based).
+    fn error(&self, offset: usize, reason: impl Into<String>) -> AppError {
+        AppError::InvalidRange {
+            input: self.input.to_string(),
+            position: self.input[..offset.min(self.input.len())].chars().count() + 1,
+            reason: reason.into(),
+        }
+    }
+
+    fn term(&self, term: &str, offset: usize) -> Result<Vec<usize>> {
+        if term.eq_ignore_ascii_case("all") {
+            return Ok((1..=self.max).collect());
+        }
+
+        if let Some(count) = keyword_arg(term, "last") {
+            let n = self.count(count, offset + (term.len() - count.len()))?;
+            return Ok((self.max.saturating_sub(n) + 1..=self.max).collect());
         }
 
-        if let Some((start_str, end_str)) = part.split_once('-') {
-            let start: usize = start_str
-                .trim()
-                .parse()
-                .map_err(|_| AppError::InvalidRange(format!("invalid number in \"{part}\"")))?;
-            let end: usize = end_str
-                .trim()
-                .parse()
-                .map_err(|_| AppError::InvalidRange(format!("invalid number in \"{part}\"")))?;
-
-            if start == 0 || end == 0 {
-                return Err(AppError::InvalidRange("indices start at 1".into()));
+        if let Some(count) = keyword_arg(term, "random") {
+            let n = self.count(count, offset + (term.len() - count.len()))?;
+            let mut pool: Vec<usize> = (1..=self.max).collect();
+            fastrand::shuffle(&mut pool);
+            pool.truncate(n);
+            return Ok(pool);
+        }
+
+        let (range, step) = match term.split_once(':') {
+            Some((range, step)) => {
+                let step_offset = offset + range.len() + 1 + leading_ws(step);
+                let step = self.number(step.trim(), step_offset)?;
+                if step == 0 {
+                    return Err(self.error(step_offset, "step must be at least 1"));
+                }
+                (range.trim_end(), step)
             }
-            if start > end {
-                return Err(AppError::InvalidRange(format!("{start} > {end}")));
+            None => (term, 1),
+        };
+
+        if let Some((start_raw, end_raw)) = range.split_once('-') {
+            let end_offset = offset + start_raw.len() + 1 + leading_ws(end_raw);
+            let start_str = start_raw.trim();
+            let end_str = end_raw.trim();
+
+            if start_str.is_empty() && end_str.is_empty() {
+                return Err(self.error(offset, "range needs at least one bound"));
             }
-            if end > max {
-                return Err(AppError::InvalidRange(format!(
-                    "{end} exceeds playlist size ({max})"
-                )));
+
+            let start = if start_str.is_empty() {
+                1
+            } else {
+                self.index(start_str, offset)?
+            };
+            let end = if end_str.is_empty() {
+                self.max
+            } else {
+                self.index(end_str, end_offset)?
+            };
+
+            if start > end {
+                return Err(self.error(offset, format!("{start} > {end}")));
             }
 
-            indices.extend(start..=end);
+            Ok((start..=end).step_by(step).collect())
         } else {
-            let idx: usize = part
-                .parse()
-                .map_err(|_| AppError::InvalidRange(format!("invalid number \"{part}\"")))?;
+            if step != 1 {
+                return Err(self.error(offset, "a step needs a range (e.g. 1-20:2)"));
+            }
+            Ok(vec![self.index(range, offset)?])
+        }
+    }
 
-            if idx == 0 {
-                return Err(AppError::InvalidRange("indices start at 1".into()));
+    /// A single index, either `N` or `^N` (from the end).
+    fn index(&self, s: &str, offset: usize) -> Result<usize> {
+        if let Some(from_end) = s.strip_prefix('^') {
+            let n = self.number(from_end, offset + 1)?;
+            if n == 0 {
+                return Err(self.error(offset, "indices from the end start at ^1"));
             }
-            if idx > max {
-                return Err(AppError::InvalidRange(format!(
-                    "{idx} exceeds playlist size ({max})"
-                )));
+            if n > self.max {
+                return Err(self.error(offset, format!("^{n} exceeds list size ({})", self.max)));
             }
+            return Ok(self.max + 1 - n);
+        }
 
-            indices.push(idx);
+        let idx = self.number(s, offset)?;
+        if idx == 0 {
+            return Err(self.error(offset, "indices start at 1"));
         }
+        if idx > self.max {
+            return Err(self.error(offset, format!("{idx} exceeds list size ({})", self.max)));
+        }
+        Ok(idx)
+    }
+
+    /// The count argument of `last N` / `random N`, clamped to the list size.
+    fn count(&self, s: &str, offset: usize) -> Result<usize> {
+        let n = self.number(s, offset)?;
+        if n == 0 {
+            return Err(self.error(offset, "count must be at least 1"));
+        }
+        Ok(n.min(self.max))
     }
 
-    indices.sort_unstable();
-    indices.dedup();
+    fn number(&self, s: &str, offset: usize) -> Result<usize> {
+        s.parse()
+            .map_err(|_| self.error(offset, format!("invalid number \"{s}\"")))
+    }
+}
 
-    if indices.is_empty() {
-        return Err(AppError::InvalidRange("no indices selected".into()));
+/// If `term` is `<keyword> <arg>`, return the (trimmed) argument.
+fn keyword_arg<'a>(term: &'a str, keyword: &str) -> Option<&'a str> {
+    let head = term.get(..keyword.len())?;
+    let rest = &term[keyword.len()..];
+    if head.eq_ignore_ascii_case(keyword) && rest.starts_with(char::is_whitespace) {
+        Some(rest.trim_start())
+    } else {
+        None
     }
+}
 
-    Ok(indices)
+fn leading_ws(s: &str) -> usize {
+    s.len() - s.trim_start().len()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn position(input: &str, max: usize) -> usize {
+        match parse_ranges(input, max) {
+            Err(AppError::InvalidRange { position, .. }) => position,
+            other => panic!("expected InvalidRange, got {other:?}"),
+        }
+    }
+
     #[test]
     fn test_single() {
         assert_eq!(parse_ranges("3", 10).unwrap(), vec![3]);
@@ -111,4 +232,54 @@ mod tests {
     fn test_zero() {
         assert!(parse_ranges("0", 10).is_err());
     }
+
+    #[test]
+    fn test_open_ended() {
+        assert_eq!(parse_ranges("8-", 10).unwrap(), vec![8, 9, 10]);
+        assert_eq!(parse_ranges("-3", 10).unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_from_end() {
+        assert_eq!(parse_ranges("^1", 10).unwrap(), vec![10]);
+        assert_eq!(parse_ranges("^3-^2", 10).unwrap(), vec![8, 9]);
+        assert_eq!(parse_ranges("^2-", 10).unwrap(), vec![9, 10]);
+    }
+
+    #[test]
+    fn test_exclusions() {
+        assert_eq!(
+            parse_ranges("all,!4,!7-9", 10).unwrap(),
+            vec![1, 2, 3, 5, 6, 10]
+        );
+        assert_eq!(parse_ranges("!2-", 4).unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn test_step() {
+        assert_eq!(parse_ranges("1-9:2", 10).unwrap(), vec![1, 3, 5, 7, 9]);
+        assert!(parse_ranges("-:5", 10).is_err());
+    }
+
+    #[test]
+    fn test_last() {
+        assert_eq!(parse_ranges("last 3", 10).unwrap(), vec![8, 9, 10]);
+        assert_eq!(parse_ranges("last 20", 3).unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_random() {
+        let picked = parse_ranges("random 3", 10).unwrap();
+        assert_eq!(picked.len(), 3);
+        assert!(picked.iter().all(|&i| (1..=10).contains(&i)));
+    }
+
+    #[test]
+    fn test_error_positions() {
+        assert_eq!(position("1,x", 10), 3);
+        assert_eq!(position("1, 3-99", 10), 6);
+        assert_eq!(position("1-4:0", 10), 5);
+        assert_eq!(position("!", 10), 2);
+        assert_eq!(position("last 0", 10), 6);
+    }
 }
@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+use super::process;
+
+use crate::error::{AppError, Result};
+use crate::model::segment::{SegmentCategory, SkipSegment, SponsorBlockSegmentJson};
+use crate::model::video::{Chapter, VideoInfo};
+
+pub const SPONSORBLOCK_API: &str = "https://sponsor.ajay.app";
+
+/// Segments shorter than this are ignored when splitting sections.
+const MIN_SECTION_LEN: f64 = 0.5;
+
+pub trait SegmentSource: Send + Sync {
+    fn fetch_segments(
+        &self,
+        video_id: &str,
+        categories: &[SegmentCategory],
+    ) -> Result<Vec<SkipSegment>>;
+}
+
+/// Queries the public SponsorBlock API (through `curl`, like we do for yt-dlp).
+pub struct SponsorBlockApi {
+    pub base_url: String,
+}
+
+impl Default for SponsorBlockApi {
+    fn default() -> Self {
+        Self {
+            base_url: SPONSORBLOCK_API.to_string(),
+        }
+    }
+}
+
+/// Percent-encode everything but unreserved characters, for a query value.
+fn query_encode(value: &str) -> String {
+    let mut out = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                out.push(byte as char)
+            }
+            _ => out.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    out
+}
+
+impl SegmentSource for SponsorBlockApi {
+    fn fetch_segments(
+        &self,
+        video_id: &str,
+        categories: &[SegmentCategory],
+    ) -> Result<Vec<SkipSegment>> {
+        let categories = categories
+            .iter()
+            .map(|c| format!("\"{}\"", c.api_name()))
+            .collect::<Vec<_>>()
+            .join(",");
+        let url = format!(
+            "{}/api/skipSegments?videoID={}&categories=[{categories}]",
+            self.base_url.trim_end_matches('/'),
+            query_encode(video_id)
+        );
+
+        log::info!("Fetching SponsorBlock segments: {url}");
+        // The status code is appended on its own line so we can tell
+        // "no segments" (404) apart from real failures.
+        let output = Command::new("curl")
+            .args(["-sS", "-g", "-w", "\n%{http_code}", &url])
+            .output()
+            .map_err(|e| process::spawn_error("curl", e))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(AppError::Segments(stderr.trim().to_string()));
+        }
+
+        let body = String::from_utf8_lossy(&output.stdout);
+        let (body, status) = body.rsplit_once('\n').unwrap_or(("", body.as_ref()));
+        match status.trim() {
+            "200" => {
+                let json: Vec<SponsorBlockSegmentJson> = serde_json::from_str(body)?;
+                Ok(json.into_iter().filter_map(|s| s.into_segment()).collect())
+            }
+            "404" => Ok(Vec::new()),
+            code => Err(AppError::Segments(format!(
+                "SponsorBlock API returned HTTP {code}"
+            ))),
+        }
+    }
+}
+
+/// Reads segments from a local JSON file mapping video ids to segment lists,
+/// in the same shape as the API response:
+/// `{ "<id>": [{ "category": "sponsor", "segment": [12.0, 45.5] }] }`
+pub struct LocalSegmentFile {
+    segments: HashMap<String, Vec<SponsorBlockSegmentJson>>,
+}
+
+impl LocalSegmentFile {
+    pub fn open(path: &Path) -> Result<Self> {
+        let data = std::fs::read(path)?;
+        let segments = serde_json::from_slice(&data)?;
+        Ok(Self { segments })
+    }
+}
+
+impl SegmentSource for LocalSegmentFile {
+    fn fetch_segments(
+        &self,
+        video_id: &str,
+        categories: &[SegmentCategory],
+    ) -> Result<Vec<SkipSegment>> {
+        let Some(entries) = self.segments.get(video_id) else {
+            return Ok(Vec::new());
+        };
+        Ok(entries
+            .iter()
+            .cloned()
+            .filter_map(SponsorBlockSegmentJson::into_segment)
+            .filter(|s| categories.contains(&s.category))
+            .collect())
+    }
+}
+
+/// Sort segments and merge overlapping ones, dropping empty ranges.
+fn normalize(segments: &[SkipSegment]) -> Vec<(f64, f64)> {
+    let mut ranges: Vec<(f64, f64)> = segments
+        .iter()
+        .filter(|s| s.end > s.start)
+        .map(|s| (s.start, s.end))
+        .collect();
+    ranges.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut merged: Vec<(f64, f64)> = Vec::new();
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Remove every skip range from a single (start, end) section.
+fn subtract(section: (f64, f64), skips: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let mut pieces = Vec::new();
+    let mut cursor = section.0;
+
+    for &(skip_start, skip_end) in skips {
+        if skip_end <= cursor || skip_start >= section.1 {
+            continue;
+        }
+        if skip_start > cursor {
+            pieces.push((cursor, skip_start));
+        }
+        cursor = cursor.max(skip_end);
+    }
+    if cursor < section.1 {
+        pieces.push((cursor, section.1));
+    }
+
+    pieces
+        .into_iter()
+        .filter(|(s, e)| e - s >= MIN_SECTION_LEN)
+        .collect()
+}
+
+/// Cut the segments out of what will be downloaded.
+///
+/// If chapters were selected, the skips are removed from each selected
+/// section, which are downloaded as separate files anyway; a full video gets
+/// them as `removed_sections` and stays one file. Returns `false` and leaves
+/// the video untouched if nothing would be left to download.
+pub fn remove_segments(video: &mut VideoInfo, segments: &[SkipSegment]) -> bool {
+    let skips = normalize(segments);
+    if skips.is_empty() {
+        return true;
+    }
+
+    if !video.has_selected_sections() {
+        if video.duration.is_some_and(|d| subtract((0.0, d), &skips).is_empty()) {
+            return false;
+        }
+        video.removed_sections = skips;
+        return true;
+    }
+
+    let sections: Vec<(f64, f64)> = video
+        .selected_sections
+        .iter()
+        .flat_map(|section| subtract(*section, &skips))
+        .collect();
+    // An empty section list would mean "full video" to the downloader
+    if sections.is_empty() {
+        return false;
+    }
+
+    video.selected_sections = sections;
+    true
+}
+
+/// Expose the segments as chapters so they show up in chapter selection.
+///
+/// Existing chapters are split around each segment; a video without chapters
+/// gets a single base chapter spanning its duration.
+pub fn mark_segments(video: &mut VideoInfo, segments: &[SkipSegment]) -> bool {
+    if segments.is_empty() {
+        return true;
+    }
+
+    let base = if video.has_chapters() {
+        video.chapters.clone()
+    } else {
+        match video.duration {
+            Some(duration) => vec![Chapter {
+                title: video.title.clone(),
+                start_time: 0.0,
+                end_time: duration,
+            }],
+            None => return false,
+        }
+    };
+
+    let skips = normalize(segments);
+    let mut chapters: Vec<Chapter> = base
+        .iter()
+        .flat_map(|ch| {
+            subtract((ch.start_time, ch.end_time), &skips)
+                .into_iter()
+                .map(|(start, end)| Chapter {
+                    title: ch.title.clone(),
+                    start_time: start,
+                    end_time: end,
+                })
+        })
+        .collect();
+
+    chapters.extend(
+        segments
+            .iter()
+            .filter(|s| s.end > s.start)
+            .map(|s| Chapter {
+                title: format!("[{}]", s.category.label()),
+                start_time: s.start,
+                end_time: s.end,
+            }),
+    );
+    chapters.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+
+    video.chapters = chapters;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn video(duration: f64) -> VideoInfo {
+        VideoInfo {
+            index: 1,
+            id: "abc".into(),
+            title: "Video".into(),
+            duration: Some(duration),
+            ..Default::default()
+        }
+    }
+
+    fn seg(start: f64, end: f64) -> SkipSegment {
+        SkipSegment {
+            category: SegmentCategory::Sponsor,
+            start,
+            end,
+        }
+    }
+
+    #[test]
+    fn test_remove_from_full_video() {
+        // Still one download, with the segments cut out
+        let mut v = video(100.0);
+        assert!(remove_segments(&mut v, &[seg(0.0, 10.0), seg(50.0, 60.0)]));
+        assert!(v.selected_sections.is_empty());
+        assert_eq!(v.removed_sections, vec![(0.0, 10.0), (50.0, 60.0)]);
+        assert_eq!(v.download_duration(), Some(80.0));
+    }
+
+    #[test]
+    fn test_remove_composes_with_chapters() {
+        let mut v = video(100.0);
+        v.selected_sections = vec![(0.0, 30.0), (70.0, 100.0)];
+        remove_segments(&mut v, &[seg(20.0, 80.0)]);
+        assert_eq!(v.selected_sections, vec![(0.0, 20.0), (80.0, 100.0)]);
+    }
+
+    #[test]
+    fn test_remove_merges_overlaps() {
+        let mut v = video(100.0);
+        remove_segments(&mut v, &[seg(10.0, 30.0), seg(20.0, 40.0)]);
+        assert_eq!(v.removed_sections, vec![(10.0, 40.0)]);
+    }
+
+    #[test]
+    fn test_remove_without_duration() {
+        let mut v = video(100.0);
+        v.duration = None;
+        assert!(remove_segments(&mut v, &[seg(0.0, 10.0)]));
+        assert_eq!(v.removed_sections, vec![(0.0, 10.0)]);
+    }
+
+    #[test]
+    fn test_remove_everything_is_refused() {
+        let mut v = video(100.0);
+        v.selected_sections = vec![(10.0, 20.0)];
+        assert!(!remove_segments(&mut v, &[seg(5.0, 25.0)]));
+        assert_eq!(v.selected_sections, vec![(10.0, 20.0)]);
+
+        let mut v = video(100.0);
+        assert!(!remove_segments(&mut v, &[seg(0.0, 100.0)]));
+        assert!(v.removed_sections.is_empty());
+    }
+
+    #[test]
+    fn test_mark_splits_chapters() {
+        let mut v = video(100.0);
+        mark_segments(&mut v, &[seg(40.0, 50.0)]);
+        let spans: Vec<_> = v
+            .chapters
+            .iter()
+            .map(|c| (c.title.as_str(), c.start_time, c.end_time))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                ("Video", 0.0, 40.0),
+                ("[Sponsor]", 40.0, 50.0),
+                ("Video", 50.0, 100.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_query_encode() {
+        assert_eq!(query_encode("dQw4w9WgXcQ"), "dQw4w9WgXcQ");
+        assert_eq!(query_encode("a&b=c d"), "a%26b%3Dc%20d");
+    }
+}
@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+use crate::error::{AppError, Result};
+use crate::model::output::parse_size;
+
+/// Parse a rate like "500K", "4.2M" or "1G" (bytes per second, binary
+/// multiples, as accepted by yt-dlp's `--limit-rate`).
+pub fn parse_rate(input: &str) -> Result<u64> {
+    let s = input.trim();
+    parse_size(s.strip_suffix("/s").unwrap_or(s))
+        .filter(|rate| *rate > 0)
+        .ok_or_else(|| AppError::InvalidRate(input.to_string()))
+}
+
+/// Split a global bandwidth budget across the downloads that can run at
+/// once, so their sum never exceeds it.
+pub fn split_rate(budget: u64, concurrency: usize, items: usize) -> u64 {
+    let parallel = concurrency.min(items).max(1) as u64;
+    (budget / parallel).max(1)
+}
+
+/// Enforces a minimum delay between consecutive starts, across tasks.
+pub struct StartSpacer {
+    interval: Duration,
+    last: Mutex<Option<Instant>>,
+}
+
+impl StartSpacer {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last: Mutex::new(None),
+        }
+    }
+
+    /// Wait until `interval` has passed since the previous start.
+    pub async fn wait(&self) {
+        let mut last = self.last.lock().await;
+        if let Some(prev) = *last {
+            tokio::time::sleep_until(prev + self.interval).await;
+        }
+        *last = Some(Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rate() {
+        assert_eq!(parse_rate("500K").unwrap(), 500 * 1024);
+        assert_eq!(parse_rate("2M").unwrap(), 2 * 1024 * 1024);
+        assert_eq!(parse_rate("1.5MiB/s").unwrap(), 1536 * 1024);
+        assert_eq!(parse_rate("1000").unwrap(), 1000);
+        assert!(parse_rate("fast").is_err());
+        assert!(parse_rate("0").is_err());
+    }
+
+    #[test]
+    fn test_split_rate() {
+        assert_eq!(split_rate(6000, 6, 100), 1000);
+        // Fewer items than slots: each gets a bigger share
+        assert_eq!(split_rate(6000, 6, 2), 3000);
+    }
+}
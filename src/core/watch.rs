@@ -0,0 +1,164 @@
+use std::collections::HashSet;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::error::{AppError, Result};
+use crate::model::format::MediaFormat;
+use crate::model::video::VideoInfo;
+
+/// A watched playlist or channel.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WatchSource {
+    pub url: String,
+    /// The default format if omitted
+    #[serde(default, deserialize_with = "format_name")]
+    pub format: Option<MediaFormat>,
+    /// Relative paths are resolved against the default output directory
+    #[serde(default)]
+    pub output: Option<PathBuf>,
+}
+
+fn format_name<'de, D>(d: D) -> std::result::Result<Option<MediaFormat>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let name = String::deserialize(d)?;
+    MediaFormat::from_extension(&name)
+        .map(Some)
+        .ok_or_else(|| serde::de::Error::custom(format!("unknown format \"{name}\"")))
+}
+
+impl WatchSource {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            format: None,
+            output: None,
+        }
+    }
+}
+
+/// Read a JSON array of sources, e.g.
+/// `[{"url": "...", "format": "opus", "output": "talks"}]`.
+pub fn load_sources(path: &Path) -> Result<Vec<WatchSource>> {
+    let invalid = |reason: String| {
+        AppError::InvalidSources(format!("{}: {reason}", path.display()))
+    };
+    let json = std::fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+    serde_json::from_str(&json).map_err(|e| invalid(e.to_string()))
+}
+
+/// Parse an interval like `90`, `90s`, `30m`, `6h` or `1d`.
+pub fn parse_interval(input: &str) -> Result<Duration> {
+    let input = input.trim();
+    let split = input.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let scale = match unit.to_lowercase().as_str() {
+        "" | "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        "d" => 86_400.0,
+        _ => return Err(AppError::InvalidInterval(input.to_string())),
+    };
+    number
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|n| *n > 0.0)
+        .and_then(|n| Duration::try_from_secs_f64(n * scale).ok())
+        .ok_or_else(|| AppError::InvalidInterval(input.to_string()))
+}
+
+/// Ids of the items already downloaded, stored one per line so the file can
+/// be edited by hand.
+pub struct Archive {
+    path: PathBuf,
+    ids: HashSet<String>,
+}
+
+impl Archive {
+    /// A missing file is an empty archive.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let ids = match std::fs::read_to_string(&path) {
+            Ok(text) => text
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty())
+                .map(String::from)
+                .collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashSet::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self { path, ids })
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.ids.contains(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Record `id`, appending it to the file.
+    pub fn add(&mut self, id: &str) -> Result<()> {
+        if self.ids.insert(id.to_string()) {
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+            writeln!(file, "{id}")?;
+        }
+        Ok(())
+    }
+
+    /// The videos not recorded yet.
+    pub fn new_entries(&self, videos: Vec<VideoInfo>) -> Vec<VideoInfo> {
+        videos
+            .into_iter()
+            .filter(|v| !self.contains(&v.id))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(parse_interval("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_interval("30m").unwrap(), Duration::from_secs(1800));
+        assert_eq!(parse_interval("1.5h").unwrap(), Duration::from_secs(5400));
+        assert!(parse_interval("0").is_err());
+        assert!(parse_interval("10w").is_err());
+        assert!(parse_interval("h").is_err());
+    }
+
+    #[test]
+    fn test_archive_round_trip() {
+        let path = std::env::temp_dir().join(format!("yt-grab-archive-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut archive = Archive::open(&path).unwrap();
+        assert!(archive.is_empty());
+        archive.add("abc").unwrap();
+        archive.add("def").unwrap();
+        archive.add("abc").unwrap();
+
+        let reopened = Archive::open(&path).unwrap();
+        assert_eq!(reopened.len(), 2);
+        assert!(reopened.contains("def"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,18 +1,125 @@
 use thiserror::Error;
 
+use crate::core::failure::FailureKind;
+use crate::model::output::format_size;
+
 #[derive(Error, Debug)]
 pub enum AppError {
-    #[error("Invalid range: {0}")]
-    InvalidRange(String),
+    #[error("Invalid range \"{input}\" at position {position}: {reason}")]
+    InvalidRange {
+        input: String,
+        position: usize,
+        reason: String,
+    },
 
-    #[error("yt-dlp error: {0}")]
-    YtDlp(String),
+    #[error("yt-dlp error: {message}")]
+    YtDlp { kind: FailureKind, message: String },
 
     #[error("No videos found in playlist")]
     EmptyPlaylist,
 
+    #[error("No tab or playlist matches \"{0}\"")]
+    UnknownTab(String),
+
+    #[error("Invalid filter: {0}")]
+    InvalidFilter(String),
+
+    #[error("Invalid rate \"{0}\" (expected e.g. 500K or 2M)")]
+    InvalidRate(String),
+
+    #[error("Invalid --add-header value (expected \"Name: Value\")")]
+    InvalidHeader,
+
+    #[error("Cookie file not found: {}", .0.display())]
+    CookieFile(std::path::PathBuf),
+
+    #[error("Invalid size \"{0}\" (expected e.g. 500M or 2G)")]
+    InvalidSize(String),
+
+    #[error("Invalid {flag} value {value} (expected a number of seconds, 0 or more)")]
+    InvalidDelay { flag: &'static str, value: f64 },
+
+    #[error(
+        "Not enough disk space in {}: about {} needed (including --min-free-space), {} free; pass --no-space-check to start anyway",
+        .dir.display(),
+        format_size(*.needed),
+        format_size(*.free)
+    )]
+    InsufficientSpace {
+        dir: std::path::PathBuf,
+        needed: u64,
+        free: u64,
+    },
+
+    #[error("All {0} item(s) were filtered out")]
+    AllFiltered(usize),
+
     #[error("Download failed for \"{title}\": {reason}")]
-    DownloadFailed { title: String, reason: String },
+    DownloadFailed {
+        title: String,
+        kind: FailureKind,
+        reason: String,
+    },
+
+    #[error("Segment lookup failed: {0}")]
+    Segments(String),
+
+    #[error("Post-processing failed for {}: {reason}", .path.display())]
+    PostProcess {
+        path: std::path::PathBuf,
+        reason: String,
+    },
+
+    #[error("Unknown format \"{0}\" (expected mp3, opus, m4a, mp4 or mkv)")]
+    UnknownFormat(String),
+
+    #[error("Unknown audio profile \"{0}\" (expected podcast, music or voice)")]
+    UnknownProfile(String),
+
+    #[error("Unknown queue order \"{0}\" (expected playlist or shortest)")]
+    InvalidOrder(String),
+
+    #[error("Cannot read queue file {}: {reason}", .path.display())]
+    QueueFile {
+        path: std::path::PathBuf,
+        reason: String,
+    },
+
+    #[error("Invalid job: {0}")]
+    InvalidJob(String),
+
+    #[error("yt-grab daemon at {addr}: {reason}")]
+    Daemon { addr: String, reason: String },
+
+    #[error("Invalid watch sources: {0}")]
+    InvalidSources(String),
+
+    #[error("Invalid interval \"{0}\" (expected e.g. 90s, 30m or 6h)")]
+    InvalidInterval(String),
+
+    #[error("Invalid base URL \"{0}\" (expected http://... or https://...)")]
+    InvalidBaseUrl(String),
+
+    #[error("Export failed: {0}")]
+    Export(String),
+
+    #[error("Hook \"{program}\" failed: {reason}")]
+    Hook { program: String, reason: String },
+
+    #[error("Invalid hook command: {0}")]
+    InvalidHook(String),
+
+    #[error("Cannot ask for {what} in non-interactive mode; pass {flag}")]
+    MissingFlag {
+        what: &'static str,
+        flag: &'static str,
+    },
+
+    #[error("{0} needs an interactive terminal")]
+    NotATerminal(&'static str),
+
+    #[error("{0} not found; install it and make sure it is on your PATH")]
+    BackendMissing(&'static str),
 
     #[error("User cancelled")]
     Cancelled,
@@ -24,4 +131,90 @@ pub enum AppError {
     Json(#[from] serde_json::Error),
 }
 
+/// Exit codes. Failures recognized from yt-dlp output use 10-19, see
+/// `FailureKind::exit_code`.
+pub const EXIT_SUCCESS: i32 = 0;
+pub const EXIT_ERROR: i32 = 1;
+/// Same as clap's own usage errors
+pub const EXIT_INVALID_ARGS: i32 = 2;
+/// Some items of the batch failed
+pub const EXIT_PARTIAL: i32 = 3;
+/// Every item of the batch failed
+pub const EXIT_ALL_FAILED: i32 = 4;
+pub const EXIT_BACKEND_MISSING: i32 = 127;
+pub const EXIT_CANCELLED: i32 = 130;
+
+impl AppError {
+    /// The yt-dlp/ffmpeg failure category, for errors that have one.
+    pub fn failure_kind(&self) -> Option<FailureKind> {
+        let known = |kind: FailureKind| Some(kind).filter(|k| *k != FailureKind::Other);
+        match self {
+            Self::YtDlp { kind, .. } | Self::DownloadFailed { kind, .. } => Some(*kind),
+            Self::PostProcess { reason, .. } => known(FailureKind::classify(reason)),
+            Self::Io(e) => known(FailureKind::classify(&e.to_string())),
+            _ => None,
+        }
+    }
+
+    /// Process exit code for a run that stops on this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::InvalidRange { .. }
+            | Self::InvalidFilter(_)
+            | Self::UnknownTab(_)
+            | Self::InvalidRate(_)
+            | Self::InvalidHeader
+            | Self::CookieFile(_)
+            | Self::InvalidSize(_)
+            | Self::InvalidDelay { .. }
+            | Self::UnknownFormat(_)
+            | Self::UnknownProfile(_)
+            | Self::InvalidOrder(_)
+            | Self::InvalidJob(_)
+            | Self::InvalidSources(_)
+            | Self::InvalidInterval(_)
+            | Self::InvalidBaseUrl(_)
+            | Self::InvalidHook(_)
+            | Self::Export(_)
+            | Self::MissingFlag { .. } => EXIT_INVALID_ARGS,
+            Self::BackendMissing(_) => EXIT_BACKEND_MISSING,
+            Self::Cancelled => EXIT_CANCELLED,
+            _ => self.failure_kind().map_or(EXIT_ERROR, |k| k.exit_code()),
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, AppError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes() {
+        let cases = [
+            (AppError::BackendMissing("yt-dlp"), EXIT_BACKEND_MISSING),
+            (AppError::InvalidRate("fast".into()), EXIT_INVALID_ARGS),
+            (
+                AppError::MissingFlag {
+                    what: "item selection",
+                    flag: "--items",
+                },
+                EXIT_INVALID_ARGS,
+            ),
+            (AppError::Cancelled, EXIT_CANCELLED),
+            (
+                AppError::DownloadFailed {
+                    title: "a".into(),
+                    kind: FailureKind::Private,
+                    reason: "Private video".into(),
+                },
+                11,
+            ),
+            (AppError::Segments("timed out".into()), EXIT_ERROR),
+        ];
+        for (error, code) in cases {
+            assert_eq!(error.exit_code(), code, "{error}");
+        }
+    }
+}
@@ -0,0 +1,23 @@
+//! Select and download YouTube playlist items with yt-dlp.
+//!
+//! `Grabber` runs the whole pipeline and reports progress as
+//! `ProgressEvent`s; the pieces it is built from (fetcher, downloader,
+//! range parser) are exported too, for callers that need more control.
+
+pub mod core;
+pub mod error;
+pub mod model;
+
+pub use crate::core::daemon::{Daemon, Job, JobRequest};
+pub use crate::core::downloader::{download_all, DownloadResult, Downloader, YtDlpDownloader};
+pub use crate::core::failure::FailureKind;
+pub use crate::core::filter::EntryFilter;
+pub use crate::core::grabber::{Grabber, GrabberBuilder, Playlist};
+pub use crate::core::playlist::{Listing, PlaylistFetcher, YtDlpFetcher};
+pub use crate::core::progress::{ProgressEvent, ProgressReporter};
+pub use crate::core::queue::DownloadQueue;
+pub use crate::core::range_parser::parse_ranges;
+pub use crate::error::{AppError, Result};
+pub use crate::model::config::{DownloadConfig, QueueOrder};
+pub use crate::model::format::{AudioCodec, MediaFormat, VideoCodec};
+pub use crate::model::video::VideoInfo;
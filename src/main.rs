@@ -1,33 +1,77 @@
 mod cli;
-mod core;
-mod error;
-mod model;
+mod tui;
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
 
 use clap::Parser;
 use console::Style;
 use indicatif::MultiProgress;
+use tokio_util::sync::CancellationToken;
 
-use cli::args::Args;
+use cli::args::{Args, Command, ExportArgs, PromptMode, ResumeArgs};
+use cli::daemon;
+use cli::feed;
 use cli::interactive;
-use core::downloader::download_all;
-use core::playlist::{PlaylistFetcher, YtDlpFetcher};
-use core::range_parser::parse_ranges;
-use model::config::DownloadConfig;
+use cli::logging;
+use cli::watch;
+use yt_grab::core::disk::{estimate_batch, free_space};
+use yt_grab::core::downloader::{
+    expected_files, remove_partial_files, DownloadResult, YtDlpDownloader,
+};
+use yt_grab::core::export::{render, write_exports, ExportFormat, ExportItem};
+use yt_grab::core::hooks;
+use yt_grab::core::grabber::{Grabber, Playlist};
+use yt_grab::core::playlist::{Listing, VideoDetails, YtDlpFetcher};
+use yt_grab::core::process::{self, command_line};
+use yt_grab::core::progress::{BarReporter, LogReporter};
+use yt_grab::core::queue::SavedQueue;
+use yt_grab::core::range_parser::parse_ranges;
+use yt_grab::core::sponsorblock::{
+    mark_segments, remove_segments, LocalSegmentFile, SegmentSource, SponsorBlockApi,
+};
+use yt_grab::error::{self, AppError, EXIT_ALL_FAILED, EXIT_PARTIAL, EXIT_SUCCESS};
+use yt_grab::model::config::DownloadConfig;
+use yt_grab::model::format::{AudioCodec, MediaFormat};
+use yt_grab::model::segment::{SegmentCategory, SkipMode, SkipSegment};
+use yt_grab::model::output::format_size;
+use yt_grab::model::video::{format_time, VideoInfo};
+
+type SegmentSkip = Option<(SkipMode, Vec<SegmentCategory>)>;
 
 #[tokio::main]
 async fn main() {
-    if let Err(e) = run().await {
-        let err_style = Style::new().bold().red();
-        eprintln!("\n  {} {e}", err_style.apply_to("Error:"));
-        std::process::exit(1);
+    match run().await {
+        Ok(code) => std::process::exit(code),
+        Err(e) => {
+            let err_style = Style::new().bold().red();
+            eprintln!("\n  {} {e}", err_style.apply_to("Error:"));
+            if let Some(kind) = e.failure_kind() {
+                eprintln!("  {} {}", Style::new().bold().apply_to("Hint:"), kind.hint());
+            }
+            std::process::exit(e.exit_code());
+        }
     }
 }
 
-async fn run() -> error::Result<()> {
+/// Returns the process exit code.
+async fn run() -> error::Result<i32> {
     let args = Args::parse();
+    logging::init(args.log_level(), args.log_file.as_deref())?;
+    match &args.command {
+        Some(Command::Export(export)) => {
+            return run_export(&args, export).await.map(|()| EXIT_SUCCESS)
+        }
+        Some(Command::Resume(resume)) => return run_resume(&args, resume).await,
+        Some(Command::Serve(serve)) => return daemon::run_serve(&args, serve).await,
+        Some(Command::Submit(submit)) => return daemon::run_submit(&args, submit).await,
+        Some(Command::Jobs(jobs)) => return daemon::run_jobs(jobs).await,
+        Some(Command::Watch(watch)) => return watch::run_watch(&args, watch).await,
+        Some(Command::Feed(feed_args)) => return feed::run_feed(feed_args).await,
+        None => {}
+    }
     let header = Style::new().bold().cyan();
-    let success = Style::new().bold().green();
-    let err_style = Style::new().bold().red();
 
     // Fetch playlist/video info
     println!(
@@ -35,10 +79,477 @@ async fn run() -> error::Result<()> {
         header.apply_to("yt-grab")
     );
 
-    let fetcher = YtDlpFetcher;
-    let (playlist_title, videos) = fetcher.fetch(&args.url)?;
+    let export_formats = args.export_formats()?;
+    let segment_skip = args.parse_segment_skip().map_err(AppError::Segments)?;
+    let segment_source: Arc<dyn SegmentSource> = match &args.segments_file {
+        Some(path) => Arc::new(LocalSegmentFile::open(path)?),
+        None => Arc::new(SponsorBlockApi::default()),
+    };
+
+    let mut grabber = Grabber::builder()
+        .output_dir(args.output.clone())
+        .concurrency(args.concurrency)
+        .order(args.queue_order()?)
+        .rate_limit(args.rate_limit()?)
+        .sleep_interval(args.sleep_interval()?)
+        .sleep_requests(args.sleep_requests()?)
+        .min_free_space(args.min_free_space()?)
+        .network(args.network()?)
+        .audio(args.audio_processing()?)
+        .hooks(args.hooks()?)
+        .fail_fast(args.fail_fast)
+        .write_info_json(args.write_info_json)
+        .filter(args.entry_filter()?)
+        .tabs(args.tab.clone())
+        .build();
+    let listing = grabber.list(args.url())?;
+    let chosen = select_tabs(&args, &listing)?;
+    if listing.has_playlists() {
+        println!(
+            "  {} Listing {} tab(s) or playlist(s)...",
+            header.apply_to(">>"),
+            chosen.as_ref().map_or(listing.playlists().count(), Vec::len)
+        );
+    }
+    let playlist = grabber.expand(listing, chosen.as_deref())?;
+
+    let prompt_mode = args.prompt_mode();
+    if args.tui && prompt_mode != PromptMode::Interactive {
+        return Err(AppError::NotATerminal("--tui"));
+    }
+
+    let (mut selected, format) = if args.tui {
+        select_with_tui(&args, grabber.fetcher(), &playlist, &segment_skip, &segment_source).await?
+    } else {
+        select_with_prompts(&args, grabber.fetcher(), &playlist, &segment_skip, &*segment_source)
+            .await?
+    };
+
+    if matches!(segment_skip, Some((SkipMode::Remove, _))) {
+        for video in &mut selected {
+            let segments = fetch_segments(&*segment_source, &segment_skip, video);
+            apply_removal(video, &segments);
+        }
+    }
+
+    println!("  {} Format: {format}\n", header.apply_to(">>"));
+    grabber.set_format(format);
+    let selected = Playlist {
+        videos: selected,
+        ..playlist
+    };
+    let config = grabber.config();
+
+    // A dry run only warns
+    check_disk_space(&selected.videos, config, args.no_space_check || args.dry_run)?;
+
+    if args.dry_run {
+        print_plan(&selected.videos, config);
+        return Ok(EXIT_SUCCESS);
+    }
+
+    // Download. Reporters are attached first, to see the items being queued
+    let cancel = CancellationToken::new();
+    if !args.tui {
+        spawn_interrupt_handler(cancel.clone());
+        attach_reporter(&args, &grabber);
+    }
+    let queue = grabber.queue();
+    if let Some(path) = &args.queue_file {
+        let config = DownloadConfig {
+            playlist_title: selected.title.clone(),
+            ..config.clone()
+        };
+        queue.persist(path, &config);
+    }
+    let first = match &args.first {
+        Some(items) => {
+            let last = selected.videos.iter().map(|v| v.index).max().unwrap_or(0);
+            parse_ranges(items, last)?
+        }
+        None => Vec::new(),
+    };
+    for video in &selected.videos {
+        queue.push(video.clone(), i32::from(first.contains(&video.index)));
+    }
+    queue.close();
+
+    let started_at = SystemTime::now();
+    let results = if args.tui {
+        tui::download(&grabber, &queue, selected.title.clone(), cancel.clone()).await?
+    } else {
+        let results = grabber
+            .download_queue(&queue, selected.title.clone(), cancel.clone())
+            .await;
+        logging::set_progress(None);
+        results
+    };
+
+    if cancel.is_cancelled() && args.delete_partial {
+        let expected: Vec<PathBuf> = selected
+            .videos
+            .iter()
+            .zip(&results)
+            .filter(|(_, result)| result.cancelled)
+            .flat_map(|(video, _)| expected_files(video, config))
+            .collect();
+        let removed = remove_partial_files(&config.output_dir, &expected, started_at)?;
+        println!(
+            "\n  {} Removed {} partial file(s)",
+            header.apply_to(">>"),
+            removed.len()
+        );
+    }
+
+    print_summary(&results, &args, &config.output_dir);
+
+    if !export_formats.is_empty() {
+        let items: Vec<ExportItem> = selected
+            .videos
+            .iter()
+            .zip(&results)
+            .map(|(video, result)| ExportItem {
+                video,
+                result: Some(result),
+            })
+            .collect();
+        let title = selected.title.as_deref();
+        for path in write_exports(&export_formats, &config.output_dir, title, args.url(), &items)? {
+            println!("  {} {}", header.apply_to("Playlist:"), path.display());
+        }
+        println!();
+    }
+
+    if let (Some(hook), false) = (&config.hooks.after_batch, cancel.is_cancelled()) {
+        let config = DownloadConfig {
+            playlist_title: selected.title.clone(),
+            ..config.clone()
+        };
+        let vars = hooks::batch_vars(&results, &config);
+        if let Err(e) = hooks::run_hook(hook, &vars, &cancel).await {
+            println!("  {} {e}\n", Style::new().bold().red().apply_to("!!"));
+        }
+    }
+
+    if cancel.is_cancelled() {
+        return Err(AppError::Cancelled);
+    }
+    Ok(batch_exit_code(&results))
+}
+
+fn batch_exit_code(results: &[DownloadResult]) -> i32 {
+    let succeeded = results.iter().filter(|r| r.success).count();
+    if succeeded == results.len() {
+        EXIT_SUCCESS
+    } else if succeeded == 0 {
+        EXIT_ALL_FAILED
+    } else {
+        EXIT_PARTIAL
+    }
+}
+
+/// Show download progress as bars on a terminal, or as log lines otherwise.
+fn attach_reporter(args: &Args, grabber: &Grabber) {
+    if args.quiet {
+        return;
+    }
+    if args.show_progress_bars() {
+        let multi = MultiProgress::new();
+        logging::set_progress(Some(multi.clone()));
+        grabber.events().attach(Arc::new(BarReporter::new(multi)));
+    } else {
+        grabber.events().attach(Arc::new(LogReporter::default()));
+    }
+}
+
+/// The `resume` command: download what a `--queue-file` run left unfinished,
+/// with the settings it was started with.
+async fn run_resume(args: &Args, resume: &ResumeArgs) -> error::Result<i32> {
+    let saved = SavedQueue::open(&resume.file)?;
+    let mut builder = saved
+        .builder()
+        .concurrency(resume.concurrency)
+        .network(args.network()?);
+    if let Some(delay) = args.sleep_requests()? {
+        builder = builder.sleep_requests(Some(delay));
+    }
+    if args.write_info_json {
+        builder = builder.write_info_json(true);
+    }
+    let grabber = builder.build();
+
+    let cancel = CancellationToken::new();
+    spawn_interrupt_handler(cancel.clone());
+    attach_reporter(args, &grabber);
+    let queue = grabber.queue();
+    queue.persist(&resume.file, grabber.config());
+    // Paused items are resumed too: nothing could resume them later
+    for entry in saved.unfinished() {
+        queue.push(entry.video.clone(), entry.priority);
+    }
+    queue.close();
+
+    let total = queue.entries().len();
+    println!(
+        "\n  {} Resuming {total} of {} item(s) as {} into {}\n",
+        Style::new().bold().cyan().apply_to("yt-grab"),
+        saved.entries.len(),
+        saved.format,
+        saved.output_dir.display()
+    );
+    if total == 0 {
+        return Ok(EXIT_SUCCESS);
+    }
+
+    let results = grabber
+        .download_queue(&queue, saved.playlist_title.clone(), cancel.clone())
+        .await;
+    logging::set_progress(None);
+
+    print_summary(&results, args, &saved.output_dir);
+    if cancel.is_cancelled() {
+        return Err(AppError::Cancelled);
+    }
+    Ok(batch_exit_code(&results))
+}
+
+/// The `export` command: fetch, filter and select like a download would, then
+/// write a playlist file referencing the videos' URLs.
+async fn run_export(args: &Args, export: &ExportArgs) -> error::Result<()> {
+    let format: ExportFormat = export.format.parse().map_err(AppError::Export)?;
+    let grabber = Grabber::builder()
+        .sleep_requests(args.sleep_requests()?)
+        .network(args.network()?)
+        .filter(args.entry_filter()?)
+        .tabs(args.tab.clone())
+        .build();
+
+    // Status goes to stderr so the playlist can be piped
+    eprintln!(
+        "\n  {} Fetching info from URL...",
+        Style::new().bold().cyan().apply_to("yt-grab")
+    );
+    let mut playlist = grabber.fetch(&export.url)?;
+    if let Some(items) = &args.items {
+        playlist.select(items)?;
+    }
+    let Playlist {
+        title: playlist_title,
+        mut videos,
+        ..
+    } = playlist;
+
+    if export.chapters && playlist_title.is_some() {
+        for (i, video) in videos.iter_mut().enumerate() {
+            if let Some(delay) = grabber.config().sleep_requests.filter(|_| i > 0) {
+                tokio::time::sleep(delay).await;
+            }
+            match grabber.fetcher().fetch_details(&video.url) {
+                Ok(details) => {
+                    video.chapters = details.chapters;
+                    video.sizes = details.sizes;
+                }
+                Err(e) => eprintln!(
+                    "  {} No chapters for \"{}\": {e}",
+                    Style::new().bold().red().apply_to("!!"),
+                    video.title
+                ),
+            }
+        }
+    }
+
+    let items: Vec<ExportItem> = videos
+        .iter()
+        .map(|video| ExportItem {
+            video,
+            result: None,
+        })
+        .collect();
+    let base = match &export.output {
+        Some(path) => path.parent().unwrap_or(std::path::Path::new(".")),
+        None => std::path::Path::new("."),
+    };
+    let rendered = render(format, playlist_title.as_deref(), &export.url, &items, base)?;
+
+    match &export.output {
+        Some(path) => {
+            std::fs::write(path, rendered)?;
+            eprintln!(
+                "  {} {} item(s) written to {}\n",
+                Style::new().bold().cyan().apply_to(">>"),
+                items.len(),
+                path.display()
+            );
+        }
+        None => print!("{rendered}"),
+    }
+    Ok(())
+}
+
+/// Print what a download would do: per item the files, sections, format and
+/// the exact yt-dlp command line.
+fn print_plan(videos: &[VideoInfo], config: &DownloadConfig) {
+    let header = Style::new().bold().cyan();
+    let dim = Style::new().dim();
+    let downloader = YtDlpDownloader::for_batch(config, videos.len());
+
+    println!(
+        "  {} {} item(s), nothing will be downloaded\n",
+        Style::new().bold().yellow().apply_to("Dry run:"),
+        videos.len()
+    );
+
+    for video in videos {
+        println!(
+            "  {} {} {}",
+            Style::new().yellow().apply_to(format!("[{}]", video.index)),
+            video.title,
+            dim.apply_to(format!("({})", video.duration_display()))
+        );
+        for path in expected_files(video, config) {
+            println!("      {} {}", dim.apply_to("File:    "), path.display());
+        }
+        let sections = if video.has_selected_sections() {
+            video
+                .selected_sections
+                .iter()
+                .map(|(start, end)| format!("{}-{}", format_time(*start), format_time(*end)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        } else if !video.removed_sections.is_empty() {
+            let cuts: Vec<String> = video
+                .removed_sections
+                .iter()
+                .map(|(start, end)| format!("{}-{}", format_time(*start), format_time(*end)))
+                .collect();
+            format!("full video without {}", cuts.join(", "))
+        } else {
+            "full video".to_string()
+        };
+        println!("      {} {sections}", dim.apply_to("Sections:"));
+        println!("      {} {}", dim.apply_to("Format:  "), config.format);
+
+        let command = command_line("yt-dlp", &downloader.build_args(video, config));
+        println!(
+            "      {} {}\n",
+            dim.apply_to("Command: "),
+            config.network.redact(&command)
+        );
+    }
+
+    let known: Vec<f64> = videos.iter().filter_map(|v| v.download_duration()).collect();
+    let total: f64 = known.iter().sum();
+    let mut summary = format!("{} total", format_time(total));
+    if known.len() < videos.len() {
+        summary.push_str(&format!(
+            " ({} item(s) of unknown length not counted)",
+            videos.len() - known.len()
+        ));
+    }
+    let estimate = estimate_batch(videos, config.format);
+    if estimate.bytes > 0 {
+        summary.push_str(&format!(", ~{} estimated", format_size(estimate.bytes)));
+    }
+    println!("  {} {summary}\n", header.apply_to("Plan:"));
+}
+
+/// Compare the estimated batch size with the free space in the output
+/// directory. Fails if it won't fit, unless `warn_only`.
+fn check_disk_space(
+    videos: &[VideoInfo],
+    config: &DownloadConfig,
+    warn_only: bool,
+) -> error::Result<()> {
+    let estimate = estimate_batch(videos, config.format);
+    let Some(free) = free_space(&config.output_dir) else {
+        return Ok(());
+    };
+
+    let mut line = format!("~{} estimated", format_size(estimate.bytes));
+    if estimate.unknown > 0 {
+        line.push_str(&format!(" ({} item(s) unknown)", estimate.unknown));
+    }
+    println!(
+        "  {} {line}, {} free\n",
+        Style::new().bold().cyan().apply_to("Disk:"),
+        format_size(free)
+    );
+
+    // Keep the pause threshold free, so the batch doesn't stall right away
+    let needed = estimate.bytes + config.min_free_space.unwrap_or(0);
+    if needed <= free {
+        return Ok(());
+    }
+    if !warn_only {
+        return Err(AppError::InsufficientSpace {
+            dir: config.output_dir.clone(),
+            needed,
+            free,
+        });
+    }
+    println!(
+        "  {} The downloads probably won't fit in {}\n",
+        Style::new().bold().red().apply_to("!!"),
+        config.output_dir.display()
+    );
+    Ok(())
+}
+
+/// First Ctrl-C cancels the batch (running items are stopped, queued ones
+/// skipped); a second one exits immediately.
+fn spawn_interrupt_handler(cancel: CancellationToken) {
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        eprintln!(
+            "\n  {} Cancelling... press Ctrl-C again to force quit",
+            Style::new().bold().yellow().apply_to("!!")
+        );
+        cancel.cancel();
+
+        if tokio::signal::ctrl_c().await.is_ok() {
+            process::kill_all();
+            std::process::exit(130);
+        }
+    });
+}
+
+/// Pick the tabs or playlists of a channel URL to list, by entry number.
+/// `None` lists them all.
+fn select_tabs(args: &Args, listing: &Listing) -> error::Result<Option<Vec<usize>>> {
+    if !listing.has_playlists() {
+        return Ok(None);
+    }
+    if let Some(tabs) = &args.tab {
+        return listing.choose(tabs).map(Some);
+    }
+    match args.prompt_mode() {
+        PromptMode::Interactive => interactive::prompt_tabs(listing, args.fuzzy).map(Some),
+        PromptMode::Defaults => Ok(listing.default_tabs()),
+        PromptMode::Fail => Err(AppError::MissingFlag {
+            what: "the channel tabs to list",
+            flag: "--tab or --yes",
+        }),
+    }
+}
 
-    let is_single = videos.len() == 1 && playlist_title.is_none();
+/// Pick videos, chapters and format through the text prompts (or flags).
+async fn select_with_prompts(
+    args: &Args,
+    fetcher: &YtDlpFetcher,
+    playlist: &Playlist,
+    segment_skip: &SegmentSkip,
+    segment_source: &dyn SegmentSource,
+) -> error::Result<(Vec<VideoInfo>, MediaFormat)> {
+    let Playlist {
+        title: playlist_title,
+        videos,
+        filtered_out,
+    } = playlist.clone();
+    let header = Style::new().bold().cyan();
+    let prompt_mode = args.prompt_mode();
+    let is_single = playlist.is_single();
 
     // Select videos
     let selected = if is_single {
@@ -49,14 +560,21 @@ async fn run() -> error::Result<()> {
         );
         videos
     } else {
-        interactive::display_playlist(&playlist_title, &videos);
+        interactive::display_playlist(&playlist_title, &videos, filtered_out);
 
         let indices = if args.all {
             (1..=videos.len()).collect()
         } else if let Some(ref items_str) = args.items {
             parse_ranges(items_str, videos.len())?
+        } else if prompt_mode == PromptMode::Defaults {
+            (1..=videos.len()).collect()
+        } else if prompt_mode == PromptMode::Fail {
+            return Err(AppError::MissingFlag {
+                what: "item selection",
+                flag: "--items, --all or --yes",
+            });
         } else {
-            interactive::prompt_selection(videos.len())?
+            interactive::prompt_selection(&videos, args.fuzzy)?
         };
 
         videos
@@ -73,51 +591,200 @@ async fn run() -> error::Result<()> {
 
     // Fetch chapters for each selected video and let user pick
     let mut selected = selected;
+    let mut fetched_any = false;
+    let sleep_requests = args.sleep_requests()?;
     for video in &mut selected {
         // For playlist items, chapters weren't fetched yet (flat-playlist mode)
         if !video.has_chapters() && playlist_title.is_some() {
+            if let Some(delay) = sleep_requests.filter(|_| fetched_any) {
+                tokio::time::sleep(delay).await;
+            }
+            fetched_any = true;
             println!(
                 "  {} Fetching chapters for \"{}\"...",
                 header.apply_to(">>"),
                 video.title
             );
-            match YtDlpFetcher::fetch_chapters(&video.url) {
-                Ok(chapters) if !chapters.is_empty() => {
-                    video.chapters = chapters;
+            if let Ok(details) = fetcher.fetch_details(&video.url) {
+                video.sizes = details.sizes;
+                if !details.chapters.is_empty() {
+                    video.chapters = details.chapters;
                 }
-                _ => {}
             }
         }
 
-        if video.has_chapters() && !args.no_chapters {
-            interactive::prompt_chapter_selection(video)?;
+        // Segments are marked before chapter selection so they can be picked;
+        // removal happens after selection so the cuts apply to the chosen sections.
+        if matches!(segment_skip, Some((SkipMode::Mark, _))) {
+            let segments = fetch_segments(segment_source, segment_skip, video);
+            apply_marks(video, &segments);
+        }
+
+        // Without prompts, chapters default to the full video
+        if video.has_chapters() && !args.no_chapters && prompt_mode == PromptMode::Interactive {
+            interactive::prompt_chapter_selection(video, args.fuzzy)?;
         }
     }
 
     // Select format
-    let format = match args.parse_format() {
-        Some(f) => f,
-        None => interactive::prompt_format()?,
+    let format = match (args.parse_format(), prompt_mode) {
+        (Some(f), _) => f,
+        (None, PromptMode::Interactive) => interactive::prompt_format()?,
+        (None, PromptMode::Defaults) => MediaFormat::Audio(AudioCodec::Mp3),
+        (None, PromptMode::Fail) => {
+            return Err(AppError::MissingFlag {
+                what: "the output format",
+                flag: "--format or --yes",
+            })
+        }
     };
 
-    println!("  {} Format: {format}\n", header.apply_to(">>"));
+    Ok((selected, format))
+}
+
+/// Pick videos, chapters and format in the full-screen browser.
+async fn select_with_tui(
+    args: &Args,
+    fetcher: &YtDlpFetcher,
+    playlist: &Playlist,
+    segment_skip: &SegmentSkip,
+    segment_source: &Arc<dyn SegmentSource>,
+) -> error::Result<(Vec<VideoInfo>, MediaFormat)> {
+    let Playlist {
+        title: playlist_title,
+        mut videos,
+        ..
+    } = playlist.clone();
+    let mark = matches!(segment_skip, Some((SkipMode::Mark, _)));
+
+    // Chapters already known (single video) won't go through the loader
+    if mark {
+        for video in videos.iter_mut().filter(|v| v.has_chapters()) {
+            let segments = fetch_segments(&**segment_source, segment_skip, video);
+            apply_marks(video, &segments);
+        }
+    }
+
+    let skip = segment_skip.clone();
+    let source = segment_source.clone();
+    let fetcher = fetcher.clone();
+    let loader: tui::ChapterLoader = Arc::new(move |video: &VideoInfo| {
+        let mut video = video.clone();
+        if let Ok(details) = fetcher.fetch_details(&video.url) {
+            video.chapters = details.chapters;
+            video.sizes = details.sizes;
+        }
+        if let Some((SkipMode::Mark, categories)) = &skip {
+            if let Ok(segments) = source.fetch_segments(&video.id, categories) {
+                mark_segments(&mut video, &segments);
+            }
+        }
+        VideoDetails {
+            chapters: video.chapters,
+            sizes: video.sizes,
+        }
+    });
+
+    match tui::select(playlist_title, videos, args.parse_format(), loader).await? {
+        Some(selection) => {
+            let header = Style::new().bold().cyan();
+            println!(
+                "  {} {} item(s) selected",
+                header.apply_to(">>"),
+                selection.videos.len()
+            );
+            Ok((selection.videos, selection.format))
+        }
+        None => Err(AppError::Cancelled),
+    }
+}
 
-    let config = DownloadConfig {
-        format,
-        output_dir: args.output.clone(),
-        concurrency: args.concurrency,
+/// Look up skip segments for a video, warning (not failing) on errors.
+fn fetch_segments(
+    source: &dyn SegmentSource,
+    segment_skip: &SegmentSkip,
+    video: &VideoInfo,
+) -> Vec<SkipSegment> {
+    let Some((_, categories)) = segment_skip else {
+        return Vec::new();
     };
 
-    // Download
-    let multi = MultiProgress::new();
-    let results = download_all(&selected, &config, &multi).await;
+    match source.fetch_segments(&video.id, categories) {
+        Ok(segments) => segments,
+        Err(e) => {
+            println!(
+                "  {} Skipping segments for \"{}\": {e}",
+                Style::new().bold().red().apply_to("!!"),
+                video.title
+            );
+            Vec::new()
+        }
+    }
+}
+
+fn apply_marks(video: &mut VideoInfo, segments: &[SkipSegment]) {
+    if !mark_segments(video, segments) {
+        println!(
+            "  {} Unknown duration, cannot mark segments for \"{}\"",
+            Style::new().bold().red().apply_to("!!"),
+            video.title
+        );
+    }
+}
+
+fn apply_removal(video: &mut VideoInfo, segments: &[SkipSegment]) {
+    if !remove_segments(video, segments) {
+        println!(
+            "  {} Could not remove segments from \"{}\" (nothing would be left)",
+            Style::new().bold().red().apply_to("!!"),
+            video.title
+        );
+    } else if !segments.is_empty() {
+        println!(
+            "  {} Removed {} segment(s) from \"{}\"",
+            Style::new().bold().cyan().apply_to(">>"),
+            segments.len(),
+            video.title
+        );
+    }
+}
+
+/// Title of a failed item, tagged with the failure category if known.
+fn failed_title(result: &DownloadResult) -> String {
+    match result.kind {
+        Some(kind) => format!("{} [{kind}]", result.title),
+        None => result.title.clone(),
+    }
+}
+
+fn print_summary(results: &[DownloadResult], args: &Args, output: &Path) {
+    let header = Style::new().bold().cyan();
+    let success = Style::new().bold().green();
+    let err_style = Style::new().bold().red();
+    let dim = Style::new().dim();
 
-    // Summary
     let succeeded = results.iter().filter(|r| r.success).count();
-    let failed = results.iter().filter(|r| !r.success).count();
+    let cancelled = results.iter().filter(|r| r.cancelled).count();
+    let failed = results.len() - succeeded - cancelled;
 
     println!();
-    if failed == 0 {
+    if cancelled > 0 {
+        let label = if args.fail_fast && failed > 0 {
+            "Stopped after a failure."
+        } else {
+            "Cancelled."
+        };
+        println!(
+            "  {} {succeeded} succeeded, {failed} failed, {cancelled} cancelled",
+            Style::new().bold().yellow().apply_to(label)
+        );
+        for r in results.iter().filter(|r| !r.success) {
+            match &r.error {
+                Some(err) => println!("    {} {}: {err}", err_style.apply_to("✗"), failed_title(r)),
+                None => println!("    {} {}: cancelled", Style::new().yellow().apply_to("⊘"), r.title),
+            }
+        }
+    } else if failed == 0 {
         println!(
             "  {} All {succeeded} download(s) completed!",
             success.apply_to("Done!")
@@ -127,21 +794,84 @@ async fn run() -> error::Result<()> {
             "  {} {succeeded} succeeded, {failed} failed",
             err_style.apply_to("Done.")
         );
-        for r in &results {
+        for r in results {
             if !r.success {
                 if let Some(err) = &r.error {
-                    println!("    {} {}: {err}", err_style.apply_to("✗"), r.title);
+                    println!("    {} {}: {err}", err_style.apply_to("✗"), failed_title(r));
                 }
             }
         }
     }
 
-    println!(
-        "  {} {}",
-        header.apply_to("Output:"),
-        args.output.display()
-    );
+    // One hint per kind of failure, in order of first appearance
+    let mut kinds = Vec::new();
+    for kind in results.iter().filter_map(|r| r.kind) {
+        if !kinds.contains(&kind) {
+            kinds.push(kind);
+        }
+    }
+    for kind in kinds {
+        println!("    {} {}", Style::new().bold().apply_to("Hint:"), kind.hint());
+    }
+
+    for r in results {
+        if let Some(err) = &r.hook_error {
+            println!("    {} {}: {err}", Style::new().yellow().apply_to("!"), r.title);
+        }
+    }
+
+    let files: Vec<_> = results.iter().flat_map(|r| &r.files).collect();
+    if !files.is_empty() {
+        println!("\n  {} {} file(s)", header.apply_to("Files:"), files.len());
+        for file in files {
+            println!(
+                "    {} {}",
+                file.path.display(),
+                dim.apply_to(format!(
+                    "({}, {})",
+                    file.size_display(),
+                    file.duration_display()
+                ))
+            );
+        }
+    }
+
+    println!("  {} {}", header.apply_to("Output:"), output.display());
     println!();
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(success: bool, cancelled: bool) -> DownloadResult {
+        DownloadResult {
+            title: "a".to_string(),
+            success,
+            cancelled,
+            error: (!success && !cancelled).then(|| "failed".to_string()),
+            kind: None,
+            files: Vec::new(),
+            hook_error: None,
+        }
+    }
+
+    #[test]
+    fn test_batch_exit_code() {
+        let ok = || result(true, false);
+        let failed = || result(false, false);
+        let cancelled = || result(false, true);
+        let cases = [
+            (vec![ok(), ok()], EXIT_SUCCESS),
+            (vec![ok(), failed()], EXIT_PARTIAL),
+            (vec![failed(), failed()], EXIT_ALL_FAILED),
+            (vec![], EXIT_SUCCESS),
+            // --fail-fast cancels the rest without it counting as an interrupt
+            (vec![ok(), failed(), cancelled()], EXIT_PARTIAL),
+            (vec![failed(), cancelled()], EXIT_ALL_FAILED),
+        ];
+        for (results, code) in cases {
+            assert_eq!(batch_exit_code(&results), code);
+        }
+    }
 }
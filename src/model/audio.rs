@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+
+/// ffmpeg processing applied to audio downloads after yt-dlp finishes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AudioProcessing {
+    /// EBU R128 loudness target in LUFS
+    pub normalize: Option<f64>,
+    /// Cut silence at the start and end
+    pub trim_silence: bool,
+    pub mono: bool,
+    pub sample_rate: Option<u32>,
+    /// Fade in/out length in seconds, applied to chapter clips only
+    pub fade: Option<f64>,
+}
+
+/// Level below which audio counts as silence when trimming.
+const SILENCE_THRESHOLD: &str = "-50dB";
+
+impl AudioProcessing {
+    /// Built-in presets; individual flags override their settings.
+    pub fn profile(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "podcast" => Some(Self {
+                normalize: Some(-16.0),
+                trim_silence: true,
+                mono: false,
+                sample_rate: Some(44100),
+                fade: Some(0.5),
+            }),
+            "music" => Some(Self {
+                normalize: Some(-14.0),
+                fade: Some(1.0),
+                ..Self::default()
+            }),
+            "voice" => Some(Self {
+                normalize: Some(-19.0),
+                trim_silence: true,
+                mono: true,
+                sample_rate: Some(22050),
+                fade: None,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The `-af` filter chain. `is_clip` enables the fades, which only make
+    /// sense for sections cut out of a longer recording; without silence
+    /// trimming the fade out needs the clip's `duration`.
+    pub fn filters(&self, is_clip: bool, duration: Option<f64>) -> Vec<String> {
+        let fade = self.fade.filter(|_| is_clip);
+        let mut filters = Vec::new();
+
+        // silenceremove only trims reliably from the start, so the end is
+        // trimmed (and faded out, at the trimmed length) on the reversed audio
+        if self.trim_silence {
+            let trim = format!(
+                "silenceremove=start_periods=1:start_threshold={SILENCE_THRESHOLD}:\
+                 start_silence=0.1"
+            );
+            filters.push(trim.clone());
+            filters.push("areverse".to_string());
+            filters.push(trim);
+            if let Some(secs) = fade {
+                filters.push(format!("afade=t=in:d={secs}"));
+            }
+            filters.push("areverse".to_string());
+        }
+        if let Some(secs) = fade {
+            filters.push(format!("afade=t=in:d={secs}"));
+            if let Some(duration) = duration.filter(|d| *d > secs && !self.trim_silence) {
+                filters.push(format!("afade=t=out:st={}:d={secs}", duration - secs));
+            }
+        }
+        if let Some(lufs) = self.normalize {
+            filters.push(format!("loudnorm=I={lufs}:TP=-1.5:LRA=11"));
+        }
+        filters
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filters() {
+        let only_loudness = AudioProcessing {
+            normalize: Some(-16.0),
+            ..Default::default()
+        };
+        assert_eq!(
+            only_loudness.filters(true, None),
+            vec!["loudnorm=I=-16:TP=-1.5:LRA=11"]
+        );
+
+        // Trimming never ends the output at a pause: the end is trimmed from
+        // the reversed audio
+        let podcast = AudioProcessing::profile("podcast").unwrap();
+        let full = podcast.filters(false, Some(60.0));
+        assert_eq!(full.len(), 5);
+        assert_eq!(full[0], full[2]);
+        assert_eq!([&full[1], &full[3]], ["areverse", "areverse"]);
+        assert!(!full.iter().any(|f| f.contains("stop_")));
+
+        // Fades only for clips, after the trim; the fade out is a fade in of
+        // the reversed, trimmed audio
+        let clip = podcast.filters(true, Some(60.0));
+        assert_eq!(
+            &clip[2..],
+            [
+                "silenceremove=start_periods=1:start_threshold=-50dB:start_silence=0.1",
+                "afade=t=in:d=0.5",
+                "areverse",
+                "afade=t=in:d=0.5",
+                "loudnorm=I=-16:TP=-1.5:LRA=11",
+            ]
+        );
+
+        // Without trimming the fade out is placed by the known duration
+        let music = AudioProcessing::profile("music").unwrap();
+        assert_eq!(
+            music.filters(true, Some(60.0))[..2],
+            ["afade=t=in:d=1", "afade=t=out:st=59:d=1"]
+        );
+        assert_eq!(music.filters(true, None).len(), 2);
+    }
+
+    #[test]
+    fn test_profiles() {
+        for name in ["podcast", "music", "voice"] {
+            assert!(!AudioProcessing::profile(name).unwrap().is_empty());
+        }
+        assert!(AudioProcessing::profile("loud").is_none());
+    }
+}
@@ -1,10 +1,61 @@
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
 
+use serde::{Deserialize, Serialize};
+
+use super::audio::AudioProcessing;
 use super::format::MediaFormat;
+use super::hook::HookConfig;
+use super::network::NetworkConfig;
 
 #[derive(Debug, Clone)]
 pub struct DownloadConfig {
     pub format: MediaFormat,
     pub output_dir: PathBuf,
     pub concurrency: usize,
+    /// Which waiting item starts next
+    pub order: QueueOrder,
+    /// Bandwidth budget in bytes/s shared by all concurrent downloads
+    pub rate_limit: Option<u64>,
+    /// Minimum delay between starting two downloads
+    pub sleep_interval: Option<Duration>,
+    /// Delay between metadata requests made by yt-dlp
+    pub sleep_requests: Option<Duration>,
+    /// Don't start new downloads while the output filesystem has less free
+    /// space than this, in bytes
+    pub min_free_space: Option<u64>,
+    pub network: NetworkConfig,
+    /// Applied to audio formats only
+    pub audio: AudioProcessing,
+    pub hooks: HookConfig,
+    /// Cancel the rest of the batch when an item fails
+    pub fail_fast: bool,
+    /// Keep yt-dlp's `.info.json` metadata next to each download
+    pub write_info_json: bool,
+    /// Title of the playlist being downloaded, for hook placeholders
+    pub playlist_title: Option<String>,
+}
+
+/// Which waiting item starts next, among those with the highest priority.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueOrder {
+    /// The order items were added in
+    #[default]
+    Playlist,
+    /// Shortest duration first; items of unknown length go last
+    Shortest,
+}
+
+impl FromStr for QueueOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "playlist" => Ok(Self::Playlist),
+            "shortest" => Ok(Self::Shortest),
+            other => Err(other.to_string()),
+        }
+    }
 }
@@ -1,19 +1,19 @@
 use std::fmt;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AudioCodec {
     Mp3,
     Opus,
     M4a,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum VideoCodec {
     Mp4,
     Mkv,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MediaFormat {
     Audio(AudioCodec),
     Video(VideoCodec),
@@ -48,6 +48,55 @@ impl fmt::Display for MediaFormat {
 }
 
 impl MediaFormat {
+    pub fn is_audio(&self) -> bool {
+        matches!(self, Self::Audio(_))
+    }
+
+    /// Typical bitrate of the audio yt-dlp produces at `--audio-quality 0`,
+    /// in kbit/s. Video bitrates vary too much to guess.
+    pub fn approx_bitrate(&self) -> Option<u32> {
+        match self {
+            Self::Audio(AudioCodec::Mp3) => Some(245),
+            Self::Audio(AudioCodec::Opus) => Some(160),
+            Self::Audio(AudioCodec::M4a) => Some(192),
+            Self::Video(_) => None,
+        }
+    }
+
+    /// File extension of the final output.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Audio(AudioCodec::Mp3) => "mp3",
+            Self::Audio(AudioCodec::Opus) => "opus",
+            Self::Audio(AudioCodec::M4a) => "m4a",
+            Self::Video(VideoCodec::Mp4) => "mp4",
+            Self::Video(VideoCodec::Mkv) => "mkv",
+        }
+    }
+
+    /// MIME type of the output files, e.g. for a feed enclosure.
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Audio(AudioCodec::Mp3) => "audio/mpeg",
+            Self::Audio(AudioCodec::Opus) => "audio/ogg",
+            Self::Audio(AudioCodec::M4a) => "audio/mp4",
+            Self::Video(VideoCodec::Mp4) => "video/mp4",
+            Self::Video(VideoCodec::Mkv) => "video/x-matroska",
+        }
+    }
+
+    /// The format producing files with this extension, e.g. "opus".
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_lowercase().as_str() {
+            "mp3" => Some(Self::Audio(AudioCodec::Mp3)),
+            "opus" => Some(Self::Audio(AudioCodec::Opus)),
+            "m4a" => Some(Self::Audio(AudioCodec::M4a)),
+            "mp4" => Some(Self::Video(VideoCodec::Mp4)),
+            "mkv" => Some(Self::Video(VideoCodec::Mkv)),
+            _ => None,
+        }
+    }
+
     pub fn all_options() -> &'static [(&'static str, MediaFormat)] {
         &[
             ("Audio - MP3", MediaFormat::Audio(AudioCodec::Mp3)),
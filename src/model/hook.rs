@@ -0,0 +1,161 @@
+use serde::{Deserialize, Serialize};
+
+/// A user command run after downloads. The command line is split into
+/// arguments once (with shell-style quoting, but no shell), then `{name}`
+/// placeholders are filled in per argument, so titles with spaces or quotes
+/// can't break it apart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "Vec<String>", into = "Vec<String>")]
+pub struct Hook {
+    argv: Vec<String>,
+}
+
+/// Commands to run after each finished item and after the whole batch.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HookConfig {
+    pub after_item: Option<Hook>,
+    pub after_batch: Option<Hook>,
+}
+
+impl Hook {
+    pub fn parse(command: &str) -> Result<Self, String> {
+        Self::try_from(split_command(command)?)
+    }
+
+    pub fn program(&self) -> &str {
+        &self.argv[0]
+    }
+
+    /// The arguments with `{name}` replaced by the matching variable.
+    /// Unknown placeholders are left as-is, and values are copied verbatim:
+    /// a title containing `{url}` is not expanded again.
+    pub fn expand(&self, vars: &[(&str, String)]) -> Vec<String> {
+        self.argv.iter().map(|arg| expand_arg(arg, vars)).collect()
+    }
+}
+
+/// Fill in the placeholders of one argument in a single left-to-right pass.
+fn expand_arg(arg: &str, vars: &[(&str, String)]) -> String {
+    let mut out = String::new();
+    let mut rest = arg;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let tail = &rest[open..];
+        let value = tail.find('}').and_then(|close| {
+            let name = &tail[1..close];
+            vars.iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, value)| (value, close))
+        });
+        match value {
+            Some((value, close)) => {
+                out.push_str(value);
+                rest = &tail[close + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = &tail[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+impl TryFrom<Vec<String>> for Hook {
+    type Error = String;
+
+    fn try_from(argv: Vec<String>) -> Result<Self, Self::Error> {
+        if argv.is_empty() {
+            return Err("empty command".into());
+        }
+        Ok(Self { argv })
+    }
+}
+
+impl From<Hook> for Vec<String> {
+    fn from(hook: Hook) -> Self {
+        hook.argv
+    }
+}
+
+/// Split a command line into arguments, honouring single quotes, double
+/// quotes and backslash escapes.
+fn split_command(input: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quote: Option<char> = None;
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"') | None, '\\') => {
+                current.push(chars.next().ok_or("trailing backslash")?);
+                in_arg = true;
+            }
+            (Some(_), c) => current.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+
+    if quote.is_some() {
+        return Err("unterminated quote".into());
+    }
+    if in_arg {
+        args.push(current);
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_command() {
+        assert_eq!(
+            split_command(r#"rsync -a "{path}" 'nas:/music dir/'"#).unwrap(),
+            vec!["rsync", "-a", "{path}", "nas:/music dir/"]
+        );
+        assert_eq!(split_command(r"a\ b ''").unwrap(), vec!["a b", ""]);
+        assert!(split_command("echo 'oops").is_err());
+    }
+
+    #[test]
+    fn test_expand_keeps_arguments_whole() {
+        let hook = Hook::parse("tag --title {title} {path}").unwrap();
+        let args = hook.expand(&[
+            ("title", "Rock & \"Roll\"".to_string()),
+            ("path", "/music/a b.mp3".to_string()),
+        ]);
+        assert_eq!(args, vec!["tag", "--title", "Rock & \"Roll\"", "/music/a b.mp3"]);
+    }
+
+    #[test]
+    fn test_expand_copies_values_verbatim() {
+        let hook = Hook::parse("notify {title} {url} {unknown}").unwrap();
+        let args = hook.expand(&[
+            ("title", "a {url} b".to_string()),
+            ("url", "https://example.com/{title}".to_string()),
+        ]);
+        assert_eq!(
+            args,
+            vec!["notify", "a {url} b", "https://example.com/{title}", "{unknown}"]
+        );
+    }
+}
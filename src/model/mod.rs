@@ -1,3 +1,8 @@
+pub mod audio;
 pub mod config;
 pub mod format;
+pub mod hook;
+pub mod network;
+pub mod output;
+pub mod segment;
 pub mod video;
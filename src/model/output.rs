@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+
+use super::video::format_time;
+
+/// A file written by a download (one per selected section).
+#[derive(Debug, Clone)]
+pub struct OutputFile {
+    pub path: PathBuf,
+    /// Size in bytes, if the file could be read after the download
+    pub size: Option<u64>,
+    /// Length in seconds, as reported by yt-dlp
+    pub duration: Option<f64>,
+}
+
+impl OutputFile {
+    pub fn size_display(&self) -> String {
+        match self.size {
+            Some(bytes) => format_size(bytes),
+            None => "?".to_string(),
+        }
+    }
+
+    pub fn duration_display(&self) -> String {
+        match self.duration {
+            Some(secs) => format_time(secs),
+            None => "??:??".to_string(),
+        }
+    }
+}
+
+/// Parse a size like "500K", "1.5GiB" or "1000" (binary multiples).
+pub fn parse_size(input: &str) -> Option<u64> {
+    let s = input
+        .trim()
+        .trim_end_matches(['B', 'b'])
+        .trim_end_matches(['i', 'I']);
+    let (number, multiplier) = match s.chars().last().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => (&s[..s.len() - 1], 1024.0),
+        Some('M') => (&s[..s.len() - 1], 1024.0 * 1024.0),
+        Some('G') => (&s[..s.len() - 1], 1024.0 * 1024.0 * 1024.0),
+        Some('T') => (&s[..s.len() - 1], 1024.0 * 1024.0 * 1024.0 * 1024.0),
+        _ => (s, 1.0),
+    };
+
+    let value: f64 = number.trim().parse().ok()?;
+    if value < 0.0 || !value.is_finite() {
+        return None;
+    }
+    Some((value * multiplier) as u64)
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
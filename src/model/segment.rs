@@ -0,0 +1,146 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+/// SponsorBlock segment categories.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegmentCategory {
+    Sponsor,
+    Intro,
+    Outro,
+    Selfpromo,
+    Interaction,
+    Preview,
+    Filler,
+    MusicOfftopic,
+}
+
+impl SegmentCategory {
+    pub fn all() -> &'static [SegmentCategory] {
+        &[
+            Self::Sponsor,
+            Self::Intro,
+            Self::Outro,
+            Self::Selfpromo,
+            Self::Interaction,
+            Self::Preview,
+            Self::Filler,
+            Self::MusicOfftopic,
+        ]
+    }
+
+    /// Identifier used by the SponsorBlock API.
+    pub fn api_name(&self) -> &'static str {
+        match self {
+            Self::Sponsor => "sponsor",
+            Self::Intro => "intro",
+            Self::Outro => "outro",
+            Self::Selfpromo => "selfpromo",
+            Self::Interaction => "interaction",
+            Self::Preview => "preview",
+            Self::Filler => "filler",
+            Self::MusicOfftopic => "music_offtopic",
+        }
+    }
+
+    /// Human-readable label, used as chapter title when marking.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Sponsor => "Sponsor",
+            Self::Intro => "Intro",
+            Self::Outro => "Outro",
+            Self::Selfpromo => "Self-promotion",
+            Self::Interaction => "Interaction reminder",
+            Self::Preview => "Preview/Recap",
+            Self::Filler => "Filler",
+            Self::MusicOfftopic => "Non-music section",
+        }
+    }
+}
+
+impl fmt::Display for SegmentCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.api_name())
+    }
+}
+
+impl FromStr for SegmentCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        Self::all()
+            .iter()
+            .find(|c| c.api_name() == s)
+            .copied()
+            .ok_or_else(|| format!("unknown segment category \"{s}\""))
+    }
+}
+
+/// Parse a comma-separated category list like "sponsor,intro" (or "all").
+pub fn parse_categories(input: &str) -> Result<Vec<SegmentCategory>, String> {
+    if input.trim().eq_ignore_ascii_case("all") {
+        return Ok(SegmentCategory::all().to_vec());
+    }
+
+    let mut categories = Vec::new();
+    for part in input.split(',').filter(|p| !p.trim().is_empty()) {
+        let category: SegmentCategory = part.parse()?;
+        if !categories.contains(&category) {
+            categories.push(category);
+        }
+    }
+    if categories.is_empty() {
+        return Err("no segment categories given".to_string());
+    }
+    Ok(categories)
+}
+
+/// What to do with the retrieved segments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipMode {
+    /// Cut the segments out of the downloaded sections.
+    Remove,
+    /// Keep the segments but expose them as chapters.
+    Mark,
+}
+
+/// A time range of a given category inside a video.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkipSegment {
+    pub category: SegmentCategory,
+    pub start: f64,
+    pub end: f64,
+}
+
+/// Segment JSON as returned by the SponsorBlock `skipSegments` endpoint.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SponsorBlockSegmentJson {
+    pub category: String,
+    pub segment: (f64, f64),
+}
+
+impl SponsorBlockSegmentJson {
+    /// Convert to a `SkipSegment`, dropping categories we don't know about
+    /// (e.g. `poi_highlight`, which is a point rather than a range).
+    pub fn into_segment(self) -> Option<SkipSegment> {
+        let category = self.category.parse().ok()?;
+        Some(SkipSegment {
+            category,
+            start: self.segment.0,
+            end: self.segment.1,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_categories_rejected() {
+        assert!(parse_categories("").is_err());
+        assert!(parse_categories(",").is_err());
+    }
+}
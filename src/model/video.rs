@@ -1,6 +1,6 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Chapter {
     pub title: String,
     pub start_time: f64,
@@ -13,28 +13,101 @@ impl Chapter {
     }
 }
 
-fn format_time(secs: f64) -> String {
+pub fn format_time(secs: f64) -> String {
     let total = secs as u64;
     let m = total / 60;
     let s = total % 60;
     format!("{m}:{s:02}")
 }
 
-#[derive(Debug, Clone)]
+/// Download sizes from yt-dlp's format metadata, in bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SizeInfo {
+    /// Best audio-only format
+    pub audio: Option<u64>,
+    /// The formats yt-dlp picks for a video download
+    pub video: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct VideoInfo {
     pub index: usize,
     pub id: String,
     pub title: String,
     pub duration: Option<f64>,
+    /// Upload date as `YYYYMMDD`, when yt-dlp reports it
+    pub upload_date: Option<String>,
     pub url: String,
     pub chapters: Vec<Chapter>,
     pub selected_sections: Vec<(f64, f64)>,
+    /// Ranges cut out of a full-video download (e.g. sponsor segments),
+    /// which is still written as one file
+    #[serde(default)]
+    pub removed_sections: Vec<(f64, f64)>,
+    /// Known only once full metadata has been fetched
+    pub sizes: SizeInfo,
+    /// Entry numbers down the nested playlists of a channel, e.g. `[2, 3]`
+    /// for the third video of its second tab; empty for plain playlists
+    #[serde(default)]
+    pub position: Vec<usize>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct YtDlpVideoJson {
     pub chapters: Option<Vec<Chapter>>,
+    #[serde(flatten)]
+    pub sizes: YtDlpSizeJson,
+}
+
+/// The size-related fields of a full (non-flat) yt-dlp info dict.
+#[derive(Debug, Default, Deserialize)]
+pub struct YtDlpSizeJson {
+    pub filesize: Option<f64>,
+    pub filesize_approx: Option<f64>,
+    pub requested_formats: Option<Vec<YtDlpFormatJson>>,
+    pub formats: Option<Vec<YtDlpFormatJson>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct YtDlpFormatJson {
+    pub vcodec: Option<String>,
+    pub abr: Option<f64>,
+    pub filesize: Option<f64>,
+    pub filesize_approx: Option<f64>,
+}
+
+impl YtDlpFormatJson {
+    fn size(&self) -> Option<f64> {
+        self.filesize.or(self.filesize_approx)
+    }
+
+    fn is_audio_only(&self) -> bool {
+        self.vcodec.as_deref() == Some("none")
+    }
+}
+
+impl YtDlpSizeJson {
+    pub fn size_info(&self) -> SizeInfo {
+        let requested = self
+            .requested_formats
+            .as_ref()
+            .and_then(|formats| formats.iter().map(|f| f.size()).sum::<Option<f64>>());
+        let video = requested.or(self.filesize).or(self.filesize_approx);
+
+        let audio = self.formats.as_ref().and_then(|formats| {
+            formats
+                .iter()
+                .filter(|f| f.is_audio_only() && f.size().is_some())
+                .max_by(|a, b| a.abr.unwrap_or(0.0).total_cmp(&b.abr.unwrap_or(0.0)))
+                .and_then(|f| f.size())
+        });
+
+        SizeInfo {
+            audio: audio.map(|b| b as u64),
+            video: video.map(|b| b as u64),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -47,7 +120,10 @@ pub struct YtDlpPlaylistJson {
     #[serde(rename = "fulltitle")]
     pub full_title: Option<String>,
     pub duration: Option<f64>,
+    pub upload_date: Option<String>,
     pub chapters: Option<Vec<Chapter>>,
+    #[serde(flatten)]
+    pub sizes: YtDlpSizeJson,
 }
 
 #[derive(Debug, Deserialize)]
@@ -56,6 +132,89 @@ pub struct YtDlpEntry {
     pub title: Option<String>,
     pub duration: Option<f64>,
     pub url: Option<String>,
+    pub webpage_url: Option<String>,
+    pub upload_date: Option<String>,
+    pub timestamp: Option<i64>,
+    /// `url` for flat entries, `playlist` for expanded nested playlists
+    #[serde(rename = "_type")]
+    pub kind: Option<String>,
+    /// The extractor for `url`: `Youtube` for videos, `YoutubeTab` for
+    /// channel tabs and playlists
+    pub ie_key: Option<String>,
+    /// Entries of a nested playlist, when yt-dlp expanded it
+    pub entries: Option<Vec<YtDlpEntry>>,
+}
+
+impl YtDlpEntry {
+    /// A channel tab or playlist rather than a video.
+    pub fn is_playlist(&self) -> bool {
+        self.entries.is_some()
+            || self.kind.as_deref() == Some("playlist")
+            || self.ie_key.as_deref() == Some("YoutubeTab")
+    }
+
+    /// Flat playlist entries often only carry a Unix timestamp.
+    pub fn upload_date(&self) -> Option<String> {
+        self.upload_date
+            .clone()
+            .or_else(|| self.timestamp.map(date_from_timestamp))
+    }
+}
+
+/// The `.info.json` yt-dlp writes next to a download with
+/// `--write-info-json`; only the fields a podcast feed uses.
+#[derive(Debug, Deserialize)]
+pub struct YtDlpInfoJson {
+    pub id: Option<String>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub duration: Option<f64>,
+    pub upload_date: Option<String>,
+    pub timestamp: Option<f64>,
+    pub webpage_url: Option<String>,
+    pub thumbnail: Option<String>,
+    pub channel: Option<String>,
+    pub uploader: Option<String>,
+    pub chapters: Option<Vec<Chapter>>,
+}
+
+/// Convert a Unix timestamp to a `YYYYMMDD` date (UTC).
+pub fn date_from_timestamp(ts: i64) -> String {
+    // Civil-from-days, see http://howardhinnant.github.io/date_algorithms.html
+    let days = ts.div_euclid(86_400);
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}{month:02}{day:02}")
+}
+
+/// Midnight UTC of a `YYYYMMDD` date, as a Unix timestamp.
+pub fn timestamp_from_date(date: &str) -> Option<i64> {
+    if date.len() != 8 || !date.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let (year, month, day): (i64, i64, i64) = (
+        date[..4].parse().ok()?,
+        date[4..6].parse().ok()?,
+        date[6..].parse().ok()?,
+    );
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    // Days-from-civil, the inverse of the above
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    Some((era * 146_097 + doe - 719_468) * 86_400)
 }
 
 impl VideoInfo {
@@ -66,6 +225,15 @@ impl VideoInfo {
         }
     }
 
+    /// The position within nested playlists, like `2.3`.
+    pub fn position_display(&self) -> Option<String> {
+        if self.position.is_empty() {
+            return None;
+        }
+        let parts: Vec<String> = self.position.iter().map(|n| n.to_string()).collect();
+        Some(parts.join("."))
+    }
+
     pub fn has_chapters(&self) -> bool {
         !self.chapters.is_empty()
     }
@@ -73,4 +241,21 @@ impl VideoInfo {
     pub fn has_selected_sections(&self) -> bool {
         !self.selected_sections.is_empty()
     }
+
+    /// Length of what will be downloaded: the selected sections, or the
+    /// whole video without the removed ones.
+    pub fn download_duration(&self) -> Option<f64> {
+        if self.has_selected_sections() {
+            Some(self.selected_sections.iter().map(|(s, e)| e - s).sum())
+        } else {
+            let duration = self.duration?;
+            let removed: f64 = self
+                .removed_sections
+                .iter()
+                .map(|(s, e)| e.min(duration) - s.max(0.0))
+                .filter(|len| *len > 0.0)
+                .sum();
+            Some((duration - removed).max(0.0))
+        }
+    }
 }
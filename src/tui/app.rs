@@ -0,0 +1,529 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use yt_grab::core::progress::{Phase, ProgressEvent};
+use yt_grab::core::playlist::VideoDetails;
+use yt_grab::model::format::MediaFormat;
+use yt_grab::model::video::{Chapter, VideoInfo};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChapterState {
+    NotLoaded,
+    Loading,
+    Loaded,
+}
+
+pub struct Row {
+    pub video: VideoInfo,
+    pub checked: bool,
+    pub expanded: bool,
+    pub chapter_state: ChapterState,
+    pub chapter_checked: Vec<bool>,
+}
+
+impl Row {
+    fn set_chapters(&mut self, chapters: Vec<Chapter>) {
+        self.chapter_checked = vec![false; chapters.len()];
+        self.video.chapters = chapters;
+        self.chapter_state = ChapterState::Loaded;
+    }
+
+    pub fn checked_chapters(&self) -> usize {
+        self.chapter_checked.iter().filter(|c| **c).count()
+    }
+
+    /// Included in the download if ticked itself or through any chapter.
+    pub fn is_selected(&self) -> bool {
+        self.checked || self.checked_chapters() > 0
+    }
+}
+
+/// One visible line of the browse list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Line {
+    Video(usize),
+    Chapter(usize, usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Focus {
+    List,
+    Search,
+    Format,
+}
+
+/// What the browse loop should do after a key press.
+pub enum Action {
+    None,
+    LoadChapters(usize),
+    Confirm,
+    Quit,
+}
+
+pub struct BrowseApp {
+    pub title: Option<String>,
+    pub rows: Vec<Row>,
+    pub search: String,
+    pub focus: Focus,
+    pub cursor: usize,
+    pub format_idx: usize,
+    pub format_locked: bool,
+    pub status: String,
+}
+
+impl BrowseApp {
+    pub fn new(title: Option<String>, videos: Vec<VideoInfo>, format: Option<MediaFormat>) -> Self {
+        let rows = videos
+            .into_iter()
+            .map(|video| {
+                let chapter_state = if video.has_chapters() {
+                    ChapterState::Loaded
+                } else {
+                    ChapterState::NotLoaded
+                };
+                Row {
+                    chapter_checked: vec![false; video.chapters.len()],
+                    video,
+                    checked: false,
+                    expanded: false,
+                    chapter_state,
+                }
+            })
+            .collect();
+
+        let format_idx = format
+            .and_then(|f| {
+                MediaFormat::all_options()
+                    .iter()
+                    .position(|(_, option)| *option == f)
+            })
+            .unwrap_or(0);
+
+        Self {
+            title,
+            rows,
+            search: String::new(),
+            focus: Focus::List,
+            cursor: 0,
+            format_idx,
+            format_locked: format.is_some(),
+            status: String::new(),
+        }
+    }
+
+    fn row_visible(&self, row: &Row) -> bool {
+        self.search.is_empty()
+            || row
+                .video
+                .title
+                .to_lowercase()
+                .contains(&self.search.to_lowercase())
+    }
+
+    pub fn lines(&self) -> Vec<Line> {
+        let mut lines = Vec::new();
+        for (i, row) in self.rows.iter().enumerate() {
+            if !self.row_visible(row) {
+                continue;
+            }
+            lines.push(Line::Video(i));
+            if row.expanded {
+                lines.extend((0..row.video.chapters.len()).map(|c| Line::Chapter(i, c)));
+            }
+        }
+        lines
+    }
+
+    pub fn selected_count(&self) -> usize {
+        self.rows.iter().filter(|r| r.is_selected()).count()
+    }
+
+    pub fn format(&self) -> MediaFormat {
+        MediaFormat::all_options()[self.format_idx].1
+    }
+
+    pub fn chapters_loaded(&mut self, row: usize, details: VideoDetails) {
+        let VideoDetails { chapters, sizes } = details;
+        let r = &mut self.rows[row];
+        r.video.sizes = sizes;
+        if chapters.is_empty() {
+            r.chapter_state = ChapterState::Loaded;
+            r.expanded = false;
+            self.status = format!("\"{}\" has no chapters", r.video.title);
+        } else {
+            r.set_chapters(chapters);
+        }
+    }
+
+    /// Selected videos, with `selected_sections` set from ticked chapters.
+    pub fn into_selection(self) -> Vec<VideoInfo> {
+        self.rows
+            .into_iter()
+            .filter(|r| r.is_selected())
+            .map(|r| {
+                let mut video = r.video;
+                video.selected_sections = video
+                    .chapters
+                    .iter()
+                    .zip(&r.chapter_checked)
+                    .filter(|(_, checked)| **checked)
+                    .map(|(ch, _)| (ch.start_time, ch.end_time))
+                    .collect();
+                video
+            })
+            .collect()
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Action {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Action::Quit;
+        }
+
+        match self.focus {
+            Focus::Search => self.handle_search_key(key),
+            Focus::Format => self.handle_format_key(key),
+            Focus::List => self.handle_list_key(key),
+        }
+    }
+
+    fn handle_search_key(&mut self, key: KeyEvent) -> Action {
+        match key.code {
+            KeyCode::Esc => {
+                self.search.clear();
+                self.focus = Focus::List;
+            }
+            KeyCode::Enter => self.focus = Focus::List,
+            KeyCode::Backspace => {
+                self.search.pop();
+            }
+            KeyCode::Char(c) => self.search.push(c),
+            _ => {}
+        }
+        self.cursor = 0;
+        Action::None
+    }
+
+    fn handle_format_key(&mut self, key: KeyEvent) -> Action {
+        let count = MediaFormat::all_options().len();
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.format_idx = (self.format_idx + count - 1) % count;
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.format_idx = (self.format_idx + 1) % count;
+            }
+            KeyCode::Tab | KeyCode::Esc | KeyCode::Enter => self.focus = Focus::List,
+            KeyCode::Char('d') => return self.confirm(),
+            KeyCode::Char('q') => return Action::Quit,
+            _ => {}
+        }
+        Action::None
+    }
+
+    fn handle_list_key(&mut self, key: KeyEvent) -> Action {
+        let lines = self.lines();
+        let current = lines.get(self.cursor).copied();
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Action::Quit,
+            KeyCode::Up | KeyCode::Char('k') => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.cursor = (self.cursor + 1).min(lines.len().saturating_sub(1));
+            }
+            KeyCode::PageUp => self.cursor = self.cursor.saturating_sub(10),
+            KeyCode::PageDown => {
+                self.cursor = (self.cursor + 10).min(lines.len().saturating_sub(1));
+            }
+            KeyCode::Home | KeyCode::Char('g') => self.cursor = 0,
+            KeyCode::End | KeyCode::Char('G') => self.cursor = lines.len().saturating_sub(1),
+            KeyCode::Char('/') => self.focus = Focus::Search,
+            KeyCode::Tab | KeyCode::Char('f') if !self.format_locked => {
+                self.focus = Focus::Format;
+            }
+            KeyCode::Char(' ') => match current {
+                Some(Line::Video(i)) => self.rows[i].checked = !self.rows[i].checked,
+                Some(Line::Chapter(i, c)) => {
+                    let checked = &mut self.rows[i].chapter_checked[c];
+                    *checked = !*checked;
+                }
+                None => {}
+            },
+            KeyCode::Char('a') => {
+                // Toggle every visible video: select all unless all already are
+                let visible: Vec<usize> = lines
+                    .iter()
+                    .filter_map(|l| match l {
+                        Line::Video(i) => Some(*i),
+                        Line::Chapter(..) => None,
+                    })
+                    .collect();
+                let all_checked = visible.iter().all(|&i| self.rows[i].checked);
+                for i in visible {
+                    self.rows[i].checked = !all_checked;
+                }
+            }
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => {
+                if let Some(Line::Video(i)) = current {
+                    return self.toggle_expand(i);
+                }
+            }
+            KeyCode::Left | KeyCode::Char('h') => {
+                if let Some(Line::Video(i) | Line::Chapter(i, _)) = current {
+                    self.rows[i].expanded = false;
+                    self.cursor = self
+                        .lines()
+                        .iter()
+                        .position(|l| *l == Line::Video(i))
+                        .unwrap_or(0);
+                }
+            }
+            KeyCode::Char('d') => return self.confirm(),
+            _ => {}
+        }
+        Action::None
+    }
+
+    fn toggle_expand(&mut self, i: usize) -> Action {
+        let row = &mut self.rows[i];
+        if row.expanded {
+            row.expanded = false;
+            return Action::None;
+        }
+
+        match row.chapter_state {
+            ChapterState::Loaded if row.video.has_chapters() => {
+                row.expanded = true;
+                Action::None
+            }
+            ChapterState::Loaded => {
+                self.status = format!("\"{}\" has no chapters", row.video.title);
+                Action::None
+            }
+            ChapterState::Loading => Action::None,
+            ChapterState::NotLoaded => {
+                row.chapter_state = ChapterState::Loading;
+                row.expanded = true;
+                self.status = format!("Fetching chapters for \"{}\"...", row.video.title);
+                Action::LoadChapters(i)
+            }
+        }
+    }
+
+    fn confirm(&mut self) -> Action {
+        if self.selected_count() == 0 {
+            self.status = "Nothing selected — press space to tick items".to_string();
+            Action::None
+        } else {
+            Action::Confirm
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ItemState {
+    Waiting,
+    Paused(String),
+    Downloading(f64),
+    Converting,
+    Processing(f64),
+    Done,
+    Failed(String),
+    Cancelled,
+}
+
+pub struct DownloadItem {
+    pub title: String,
+    pub state: ItemState,
+    pub priority: i32,
+}
+
+impl DownloadItem {
+    pub fn is_complete(&self) -> bool {
+        matches!(
+            self.state,
+            ItemState::Done | ItemState::Failed(_) | ItemState::Cancelled
+        )
+    }
+}
+
+/// What the download loop should do after a key press.
+pub enum DownloadAction {
+    None,
+    TogglePause(usize),
+    /// Raise (or lower, if negative) the priority of an item
+    Prioritize(usize, i32),
+    AddUrl(String),
+    Quit,
+}
+
+/// Live download view state, updated from `ProgressEvent`s.
+#[derive(Default)]
+pub struct DownloadApp {
+    pub items: Vec<DownloadItem>,
+    pub finished: bool,
+    pub cancelling: bool,
+    pub cursor: usize,
+    /// The URL being typed after pressing `a`
+    pub input: Option<String>,
+    pub status: String,
+}
+
+impl DownloadApp {
+    pub fn handle_key(&mut self, key: KeyEvent) -> DownloadAction {
+        if let Some(input) = &mut self.input {
+            match key.code {
+                KeyCode::Esc => self.input = None,
+                KeyCode::Enter => {
+                    let url = input.trim().to_string();
+                    self.input = None;
+                    if !url.is_empty() {
+                        return DownloadAction::AddUrl(url);
+                    }
+                }
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                _ => {}
+            }
+            return DownloadAction::None;
+        }
+
+        let current = self.items.get(self.cursor).filter(|it| !it.is_complete());
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.cursor = (self.cursor + 1).min(self.items.len().saturating_sub(1));
+            }
+            KeyCode::Char('q') | KeyCode::Esc | KeyCode::Enter if self.finished => {
+                return DownloadAction::Quit;
+            }
+            KeyCode::Char('p') | KeyCode::Char(' ') if current.is_some() => {
+                return DownloadAction::TogglePause(self.cursor);
+            }
+            KeyCode::Char('+') if current.is_some() => {
+                return DownloadAction::Prioritize(self.cursor, 1);
+            }
+            KeyCode::Char('-') if current.is_some() => {
+                return DownloadAction::Prioritize(self.cursor, -1);
+            }
+            KeyCode::Char('a') if !self.finished && !self.cancelling => {
+                self.input = Some(String::new());
+            }
+            _ => {}
+        }
+        DownloadAction::None
+    }
+
+    pub fn apply(&mut self, event: ProgressEvent) {
+        match event {
+            ProgressEvent::Queued { item, title, .. } => {
+                if self.items.len() <= item {
+                    self.items.resize_with(item + 1, || DownloadItem {
+                        title: String::new(),
+                        state: ItemState::Waiting,
+                        priority: 0,
+                    });
+                }
+                self.items[item].title = title;
+            }
+            ProgressEvent::Paused { item, reason } => self.set(item, ItemState::Paused(reason)),
+            ProgressEvent::Resumed { item } => self.set(item, ItemState::Waiting),
+            ProgressEvent::Started { item } => self.set(item, ItemState::Downloading(0.0)),
+            ProgressEvent::Phase { item, phase } => match phase {
+                Phase::Converting => self.set(item, ItemState::Converting),
+                Phase::Processing => self.set(item, ItemState::Processing(0.0)),
+            },
+            ProgressEvent::Progress { item, percent } => {
+                let state = match self.items.get(item).map(|it| &it.state) {
+                    Some(ItemState::Processing(_)) => ItemState::Processing(percent),
+                    _ => ItemState::Downloading(percent),
+                };
+                self.set(item, state)
+            }
+            // Progress resumes in the same state
+            ProgressEvent::Retry { .. } => {}
+            ProgressEvent::Finished { item } => self.set(item, ItemState::Done),
+            ProgressEvent::Failed { item, error } => self.set(item, ItemState::Failed(error)),
+            ProgressEvent::Cancelled { item } => self.set(item, ItemState::Cancelled),
+        }
+    }
+
+    fn set(&mut self, item: usize, state: ItemState) {
+        if let Some(it) = self.items.get_mut(item) {
+            it.state = state;
+        }
+    }
+
+    /// Overall completion in [0, 1], counting failed items as complete.
+    pub fn overall(&self) -> f64 {
+        if self.items.is_empty() {
+            return 0.0;
+        }
+        let total: f64 = self
+            .items
+            .iter()
+            .map(|it| match it.state {
+                ItemState::Waiting | ItemState::Paused(_) => 0.0,
+                ItemState::Downloading(pct) => pct / 100.0,
+                // The download part is complete
+                ItemState::Converting | ItemState::Processing(_) => 1.0,
+                ItemState::Done | ItemState::Failed(_) | ItemState::Cancelled => 1.0,
+            })
+            .sum();
+        total / self.items.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::crossterm::event::KeyEvent;
+
+    fn video(index: usize, chapters: usize) -> VideoInfo {
+        VideoInfo {
+            index,
+            id: format!("id{index}"),
+            title: format!("Video {index}"),
+            duration: Some(100.0),
+            chapters: (0..chapters)
+                .map(|c| Chapter {
+                    title: format!("Chapter {c}"),
+                    start_time: c as f64 * 10.0,
+                    end_time: (c + 1) as f64 * 10.0,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn press(app: &mut BrowseApp, code: KeyCode) -> Action {
+        app.handle_key(KeyEvent::from(code))
+    }
+
+    #[test]
+    fn test_selection_from_ticks() {
+        let mut app = BrowseApp::new(None, vec![video(1, 0), video(2, 3), video(3, 0)], None);
+
+        // Tick video 1, then expand video 2 and tick its second chapter
+        press(&mut app, KeyCode::Char(' '));
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Enter);
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Char(' '));
+
+        let selection = app.into_selection();
+        assert_eq!(selection.len(), 2);
+        assert!(selection[0].selected_sections.is_empty());
+        assert_eq!(selection[1].selected_sections, vec![(10.0, 20.0)]);
+    }
+
+    #[test]
+    fn test_search_filters_lines() {
+        let mut app = BrowseApp::new(None, vec![video(1, 0), video(12, 0), video(3, 0)], None);
+        press(&mut app, KeyCode::Char('/'));
+        press(&mut app, KeyCode::Char('1'));
+        assert_eq!(app.lines(), vec![Line::Video(0), Line::Video(1)]);
+        assert!(matches!(press(&mut app, KeyCode::Char('d')), Action::None));
+    }
+}
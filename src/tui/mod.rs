@@ -0,0 +1,257 @@
+mod app;
+mod ui;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::DefaultTerminal;
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio_util::sync::CancellationToken;
+
+use crate::cli::logging;
+use yt_grab::core::downloader::DownloadResult;
+use yt_grab::core::grabber::{Grabber, Playlist};
+use yt_grab::core::process;
+use yt_grab::core::progress::ProgressEvent;
+use yt_grab::core::playlist::VideoDetails;
+use yt_grab::core::queue::DownloadQueue;
+use yt_grab::error::Result;
+use yt_grab::model::format::MediaFormat;
+use yt_grab::model::video::VideoInfo;
+use app::{Action, BrowseApp, DownloadAction, DownloadApp};
+
+/// Loads the chapters (and download sizes) of a video when its row is
+/// expanded. Runs on a blocking thread, so it may shell out to yt-dlp.
+pub type ChapterLoader = Arc<dyn Fn(&VideoInfo) -> VideoDetails + Send + Sync>;
+
+pub struct Selection {
+    pub videos: Vec<VideoInfo>,
+    pub format: MediaFormat,
+}
+
+/// Puts the terminal back in its normal state when dropped, including on
+/// early returns through `?`. Log messages stay out of the terminal (they
+/// still reach `--log-file`) while it exists.
+struct TerminalGuard(DefaultTerminal);
+
+impl TerminalGuard {
+    fn new() -> Result<Self> {
+        let terminal = ratatui::try_init()?;
+        logging::pause_terminal(true);
+        Ok(Self(terminal))
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        ratatui::restore();
+        logging::pause_terminal(false);
+    }
+}
+
+/// Forwards key presses from a background thread, since crossterm's reader
+/// is blocking. Stops polling when dropped.
+struct KeyReader {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl KeyReader {
+    fn spawn(tx: UnboundedSender<KeyEvent>) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = stop.clone();
+        let handle = std::thread::spawn(move || {
+            while !flag.load(Ordering::Relaxed) {
+                if !event::poll(Duration::from_millis(100)).unwrap_or(false) {
+                    continue;
+                }
+                match event::read() {
+                    Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => {
+                        if tx.send(key).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(_) => break,
+                }
+            }
+        });
+        Self {
+            stop,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for KeyReader {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn is_ctrl_c(key: &KeyEvent) -> bool {
+    key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c')
+}
+
+/// Browse the playlist, tick items and chapters, and pick a format.
+/// Returns `None` if the user quit without confirming.
+pub async fn select(
+    title: Option<String>,
+    videos: Vec<VideoInfo>,
+    format: Option<MediaFormat>,
+    load_chapters: ChapterLoader,
+) -> Result<Option<Selection>> {
+    let mut app = BrowseApp::new(title, videos, format);
+    let mut terminal = TerminalGuard::new()?;
+
+    let (key_tx, mut key_rx) = mpsc::unbounded_channel();
+    let _keys = KeyReader::spawn(key_tx);
+    let (chapter_tx, mut chapter_rx) = mpsc::unbounded_channel::<(usize, VideoDetails)>();
+
+    loop {
+        terminal.0.draw(|frame| ui::draw_browse(frame, &app))?;
+
+        tokio::select! {
+            Some(key) = key_rx.recv() => match app.handle_key(key) {
+                Action::None => {}
+                Action::LoadChapters(row) => {
+                    let video = app.rows[row].video.clone();
+                    let loader = load_chapters.clone();
+                    let tx = chapter_tx.clone();
+                    tokio::task::spawn_blocking(move || {
+                        let _ = tx.send((row, loader(&video)));
+                    });
+                }
+                Action::Confirm => {
+                    let format = app.format();
+                    return Ok(Some(Selection {
+                        videos: app.into_selection(),
+                        format,
+                    }));
+                }
+                Action::Quit => return Ok(None),
+            },
+            Some((row, details)) = chapter_rx.recv() => app.chapters_loaded(row, details),
+        }
+    }
+}
+
+/// Run the batch download behind a live view of per-item progress, where
+/// items can be paused, reprioritized and added while `queue` runs.
+///
+/// The terminal is in raw mode, so Ctrl-C arrives as a key press rather than
+/// a signal: the first one cancels the batch, a second one force-quits.
+pub async fn download(
+    grabber: &Grabber,
+    queue: &DownloadQueue,
+    playlist_title: Option<String>,
+    cancel: CancellationToken,
+) -> Result<Vec<DownloadResult>> {
+    let mut app = DownloadApp::default();
+    let mut terminal = TerminalGuard::new()?;
+
+    let (key_tx, mut key_rx) = mpsc::unbounded_channel();
+    let _keys = KeyReader::spawn(key_tx);
+    // Items queued before subscribing; later ones arrive as Queued events
+    let mut event_rx = grabber.events().subscribe();
+    for (item, entry) in queue.entries().into_iter().enumerate() {
+        app.apply(ProgressEvent::Queued {
+            item,
+            id: entry.video.id,
+            title: entry.video.title,
+        });
+        app.items[item].priority = entry.priority;
+    }
+    let (fetch_tx, mut fetch_rx) = mpsc::unbounded_channel::<(String, Result<Playlist>)>();
+
+    let owned_grabber = grabber.clone();
+    let owned_queue = queue.clone();
+    let batch_cancel = cancel.clone();
+    let mut batch = tokio::spawn(async move {
+        owned_grabber
+            .download_queue(&owned_queue, playlist_title, batch_cancel)
+            .await
+    });
+    let mut results = None;
+
+    loop {
+        terminal.0.draw(|frame| ui::draw_download(frame, &app))?;
+
+        tokio::select! {
+            Some(key) = key_rx.recv() => {
+                if is_ctrl_c(&key) {
+                    if app.finished {
+                        break;
+                    }
+                    if app.cancelling {
+                        drop(terminal);
+                        process::kill_all();
+                        std::process::exit(130);
+                    }
+                    app.cancelling = true;
+                    cancel.cancel();
+                    continue;
+                }
+                match app.handle_key(key) {
+                    DownloadAction::None => {}
+                    DownloadAction::TogglePause(item) => {
+                        let paused = queue.entries().get(item).is_some_and(|e| e.paused);
+                        if paused {
+                            queue.resume(item);
+                        } else {
+                            queue.pause(item);
+                        }
+                    }
+                    DownloadAction::Prioritize(item, delta) => {
+                        if let Some(entry) = queue.entries().get(item) {
+                            let priority = entry.priority + delta;
+                            if queue.set_priority(item, priority) {
+                                app.items[item].priority = priority;
+                            }
+                        }
+                    }
+                    DownloadAction::AddUrl(url) => {
+                        app.status = format!("Fetching {url}...");
+                        let grabber = grabber.clone();
+                        let tx = fetch_tx.clone();
+                        tokio::task::spawn_blocking(move || {
+                            let playlist = grabber.fetch(&url);
+                            let _ = tx.send((url, playlist));
+                        });
+                    }
+                    DownloadAction::Quit => break,
+                }
+            }
+            Some(event) = event_rx.recv() => app.apply(event),
+            Some((url, playlist)) = fetch_rx.recv() => {
+                app.status = match playlist {
+                    Ok(playlist) => {
+                        let added = playlist
+                            .videos
+                            .into_iter()
+                            .filter_map(|video| queue.push(video, 0))
+                            .count();
+                        if added == 0 {
+                            "The batch has already finished".to_string()
+                        } else {
+                            format!("Added {added} item(s) from {url}")
+                        }
+                    }
+                    Err(e) => format!("Could not add {url}: {e}"),
+                };
+            }
+            res = &mut batch, if results.is_none() => {
+                app.finished = true;
+                results = Some(res.unwrap_or_default());
+            }
+        }
+    }
+
+    Ok(results.unwrap_or_default())
+}
@@ -0,0 +1,279 @@
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line as TextLine, Span};
+use ratatui::widgets::{Block, Borders, Gauge, List, ListItem, ListState, Paragraph};
+use ratatui::Frame;
+
+use super::app::{BrowseApp, ChapterState, DownloadApp, Focus, ItemState, Line};
+use yt_grab::model::format::MediaFormat;
+
+fn checkbox(checked: bool, partial: bool) -> &'static str {
+    match (checked, partial) {
+        (true, _) => "[x]",
+        (false, true) => "[-]",
+        (false, false) => "[ ]",
+    }
+}
+
+fn focused_block(title: &str, focused: bool) -> Block<'_> {
+    let style = if focused {
+        Style::new().fg(Color::Cyan)
+    } else {
+        Style::new().fg(Color::DarkGray)
+    };
+    Block::new()
+        .borders(Borders::ALL)
+        .border_style(style)
+        .title(title)
+}
+
+pub fn draw_browse(frame: &mut Frame, app: &BrowseApp) {
+    let [header, body, footer] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(3),
+        Constraint::Length(2),
+    ])
+    .areas(frame.area());
+    let [list_area, side] =
+        Layout::horizontal([Constraint::Min(30), Constraint::Length(24)]).areas(body);
+
+    let title = app.title.as_deref().unwrap_or("Video");
+    frame.render_widget(
+        Paragraph::new(TextLine::from(vec![
+            Span::styled(" yt-grab ", Style::new().fg(Color::Black).bg(Color::Cyan)),
+            Span::raw(format!(" {title}  ")),
+            Span::styled(
+                format!("{} of {} selected", app.selected_count(), app.rows.len()),
+                Style::new().fg(Color::Green),
+            ),
+        ])),
+        header,
+    );
+
+    draw_list(frame, app, list_area);
+    draw_formats(frame, app, side);
+    draw_footer(frame, app, footer);
+}
+
+fn draw_list(frame: &mut Frame, app: &BrowseApp, area: Rect) {
+    let lines = app.lines();
+    let items: Vec<ListItem> = lines
+        .iter()
+        .map(|line| match *line {
+            Line::Video(i) => {
+                let row = &app.rows[i];
+                let marker = match (row.expanded, row.chapter_state) {
+                    (_, ChapterState::Loading) => "…",
+                    (true, _) => "▾",
+                    (false, ChapterState::Loaded) if row.video.has_chapters() => "▸",
+                    _ => " ",
+                };
+                let chapters = if row.checked_chapters() > 0 {
+                    format!(
+                        " {}/{} ch.",
+                        row.checked_chapters(),
+                        row.video.chapters.len()
+                    )
+                } else {
+                    String::new()
+                };
+                ListItem::new(TextLine::from(vec![
+                    Span::raw(format!(
+                        "{} {marker} ",
+                        checkbox(row.checked, row.checked_chapters() > 0)
+                    )),
+                    Span::styled(
+                        format!("{:>3} ", row.video.index),
+                        Style::new().fg(Color::Yellow),
+                    ),
+                    Span::styled(
+                        row.video
+                            .position_display()
+                            .map(|p| format!("{p} "))
+                            .unwrap_or_default(),
+                        Style::new().fg(Color::DarkGray),
+                    ),
+                    Span::raw(row.video.title.clone()),
+                    Span::styled(
+                        format!(" ({}){chapters}", row.video.duration_display()),
+                        Style::new().fg(Color::DarkGray),
+                    ),
+                ]))
+            }
+            Line::Chapter(i, c) => {
+                let row = &app.rows[i];
+                let ch = &row.video.chapters[c];
+                ListItem::new(TextLine::from(vec![
+                    Span::raw(format!(
+                        "      {} ",
+                        checkbox(row.chapter_checked[c], false)
+                    )),
+                    Span::styled(format!("{:>3} ", c + 1), Style::new().fg(Color::Magenta)),
+                    Span::raw(ch.title.clone()),
+                    Span::styled(
+                        format!(" ({})", ch.time_display()),
+                        Style::new().fg(Color::DarkGray),
+                    ),
+                ]))
+            }
+        })
+        .collect();
+
+    let title = if app.search.is_empty() {
+        " Items ".to_string()
+    } else {
+        format!(" Items matching \"{}\" ", app.search)
+    };
+    let list = List::new(items)
+        .block(focused_block(&title, app.focus != Focus::Format))
+        .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+
+    let mut state = ListState::default().with_selected(Some(app.cursor));
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_formats(frame: &mut Frame, app: &BrowseApp, area: Rect) {
+    let items: Vec<ListItem> = MediaFormat::all_options()
+        .iter()
+        .enumerate()
+        .map(|(i, (label, _))| {
+            let marker = if i == app.format_idx { "(•)" } else { "( )" };
+            ListItem::new(format!("{marker} {label}"))
+        })
+        .collect();
+
+    let title = if app.format_locked {
+        " Format (--format) "
+    } else {
+        " Format "
+    };
+    let mut list = List::new(items).block(focused_block(title, app.focus == Focus::Format));
+    if app.focus == Focus::Format {
+        list = list.highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    }
+
+    let mut state = ListState::default().with_selected(Some(app.format_idx));
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_footer(frame: &mut Frame, app: &BrowseApp, area: Rect) {
+    let [status, help] =
+        Layout::vertical([Constraint::Length(1), Constraint::Length(1)]).areas(area);
+
+    let status_line = if app.focus == Focus::Search {
+        TextLine::from(vec![
+            Span::styled(" / ", Style::new().fg(Color::Black).bg(Color::Yellow)),
+            Span::raw(format!(" {}▏", app.search)),
+        ])
+    } else {
+        TextLine::from(Span::styled(
+            format!(" {}", app.status),
+            Style::new().fg(Color::Yellow),
+        ))
+    };
+    frame.render_widget(Paragraph::new(status_line), status);
+
+    let keys = match app.focus {
+        Focus::Search => " type to filter · Enter keep · Esc clear",
+        Focus::Format => " ↑↓ pick format · Tab back · d download · q quit",
+        Focus::List => {
+            " ↑↓ move · space tick · a all · Enter chapters · / search · Tab format · d download · q quit"
+        }
+    };
+    frame.render_widget(
+        Paragraph::new(keys).style(Style::new().fg(Color::DarkGray)),
+        help,
+    );
+}
+
+pub fn draw_download(frame: &mut Frame, app: &DownloadApp) {
+    let [gauge_area, list_area, footer] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(3),
+        Constraint::Length(2),
+    ])
+    .areas(frame.area());
+
+    let done = app.items.iter().filter(|it| it.is_complete()).count();
+    frame.render_widget(
+        Gauge::default()
+            .block(focused_block(" Downloading ", true))
+            .gauge_style(Style::new().fg(Color::Green))
+            .ratio(app.overall().clamp(0.0, 1.0))
+            .label(format!("{done}/{} items", app.items.len())),
+        gauge_area,
+    );
+
+    let bar_width = 20;
+    let items: Vec<ListItem> = app
+        .items
+        .iter()
+        .map(|it| {
+            let (pct, status, color) = match &it.state {
+                ItemState::Waiting => (0.0, "waiting".to_string(), Color::DarkGray),
+                ItemState::Paused(reason) => (0.0, format!("paused: {reason}"), Color::Yellow),
+                ItemState::Downloading(pct) => (*pct, format!("{pct:>5.1}%"), Color::Cyan),
+                ItemState::Converting => (100.0, "converting".to_string(), Color::Magenta),
+                ItemState::Processing(pct) => {
+                    (*pct, format!("processing {pct:>5.1}%"), Color::Magenta)
+                }
+                ItemState::Done => (100.0, "done ✓".to_string(), Color::Green),
+                ItemState::Failed(err) => (100.0, format!("FAILED ✗ {err}"), Color::Red),
+                ItemState::Cancelled => (0.0, "cancelled".to_string(), Color::Yellow),
+            };
+            let filled = ((pct / 100.0) * bar_width as f64).round() as usize;
+            let bar = format!(
+                "{}{}",
+                "━".repeat(filled.min(bar_width)),
+                "─".repeat(bar_width - filled.min(bar_width))
+            );
+            let priority = match it.priority {
+                0 => String::new(),
+                p => format!("[{p:+}] "),
+            };
+            ListItem::new(TextLine::from(vec![
+                Span::styled(format!(" {bar} "), Style::new().fg(color)),
+                Span::styled(priority, Style::new().fg(Color::Yellow)),
+                Span::raw(format!("{} ", it.title)),
+                Span::styled(status, Style::new().fg(color)),
+            ]))
+        })
+        .collect();
+
+    let mut state = ListState::default().with_selected(Some(app.cursor));
+    frame.render_stateful_widget(
+        List::new(items)
+            .block(focused_block(" Items ", false))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+        list_area,
+        &mut state,
+    );
+
+    let [status, help_area] =
+        Layout::vertical([Constraint::Length(1), Constraint::Length(1)]).areas(footer);
+    let status_line = match &app.input {
+        Some(url) => TextLine::from(vec![
+            Span::styled(" URL ", Style::new().fg(Color::Black).bg(Color::Yellow)),
+            Span::raw(format!(" {url}▏")),
+        ]),
+        None => TextLine::from(Span::styled(
+            format!(" {}", app.status),
+            Style::new().fg(Color::Yellow),
+        )),
+    };
+    frame.render_widget(Paragraph::new(status_line), status);
+
+    let help = if app.input.is_some() {
+        " type a video or playlist URL · Enter add · Esc cancel"
+    } else if app.finished {
+        " All downloads finished · q to exit"
+    } else if app.cancelling {
+        " Cancelling... Ctrl-C again to force quit"
+    } else {
+        " ↑↓ move · p pause/resume · +/- priority · a add URL · Ctrl-C cancel"
+    };
+    frame.render_widget(
+        Paragraph::new(help).style(Style::new().fg(Color::DarkGray)),
+        help_area,
+    );
+}